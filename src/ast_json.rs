@@ -0,0 +1,596 @@
+use std::{cell::RefCell, rc::Rc};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    expr::{Expr, ExprKind, KeywordArg, Literal, NodeId, Pattern, Property},
+    lox_object::LoxObject,
+    parser::{check_parameter, check_pattern, Constants},
+    scanner::Scanner,
+    stmt::{FunctionStmt, ImportStmt, MatchArm, MatchPattern, MatchStmt, Param, Stmt, StmtKind},
+    token::{Span, Token},
+};
+
+/// The whole program as JSON: `{"kind": "Program", "statements": [...]}`.
+///
+/// Every node is an object with a `kind`, its `id` and `span`, and one field
+/// per child, named the way `ExprKind` and `StmtKind` name them. Tokens keep
+/// their type, lexeme, line and column.
+pub fn to_json(stmts: &[Stmt]) -> Value {
+    json!({
+        "kind": "Program",
+        "statements": stmts.iter().map(stmt_json).collect::<Vec<Value>>(),
+    })
+}
+
+/// Rebuilds the statements `to_json` wrote. Tokens are re-scanned from their
+/// lexemes, so only `lexeme` has to be present; positions default to zero and
+/// every node gets a fresh id.
+///
+/// The tree has to pass the checks the parser makes as it reads source, like
+/// rest parameters coming last and constants being initialized and never
+/// assigned to.
+pub fn from_json(value: &Value) -> Result<Vec<Stmt>, String> {
+    let node = Node::new(value)?;
+    if node.kind != "Program" {
+        return Err(format!("Expected a Program node but got {}.", node.kind));
+    }
+    let stmts = node.array("statements")?.iter().map(stmt_from).collect::<Result<Vec<Stmt>, String>>()?;
+
+    let mut checks = Checks { constants: Constants::new(), top_level: true };
+    checks.statements(&stmts)?;
+    Ok(stmts)
+}
+
+fn token_json(token: &Token) -> Value {
+    json!({ "type": token.token_type.as_str(), "lexeme": token.lexeme, "line": token.line, "column": token.column })
+}
+
+fn span_json(span: Span) -> Value {
+    json!({ "line": span.line, "column": span.column, "end_line": span.end_line, "end_column": span.end_column })
+}
+
+// `kind` first, then where the node is, then its children.
+fn node_json(id: NodeId, span: Span, children: Value) -> Value {
+    let Value::Object(children) = children else {
+        return children;
+    };
+    let mut node = Map::new();
+    if let Some(kind) = children.get("kind") {
+        node.insert("kind".to_string(), kind.clone());
+    }
+    node.insert("id".to_string(), json!(id.0));
+    node.insert("span".to_string(), span_json(span));
+    node.extend(children.into_iter().filter(|(name, _)| name != "kind"));
+    Value::Object(node)
+}
+
+fn option_json<T>(value: Option<&T>, to_json: impl Fn(&T) -> Value) -> Value {
+    value.map(to_json).unwrap_or(Value::Null)
+}
+
+fn literal_json(literal: &Literal) -> Value {
+    match literal {
+        Literal::Number(n) => json!(n),
+        Literal::Str(s) => json!(s),
+        Literal::Boolean(b) => json!(b),
+        Literal::List(items) => Value::Array(items.borrow().iter().map(literal_json).collect()),
+        Literal::Object(object) => Value::Object(object.borrow().fields().iter()
+            .map(|(name, value)| (name.clone(), literal_json(value)))
+            .collect()),
+        // Functions and modules only exist at runtime and have no JSON form.
+        Literal::Nil | Literal::LoxCallable(_) | Literal::Namespace(_) => Value::Null,
+    }
+}
+
+fn expr_json(expr: &Expr) -> Value {
+    node_json(expr.id, expr.span, expr_kind_json(&expr.kind))
+}
+
+fn expr_kind_json(kind: &ExprKind) -> Value {
+    match kind {
+        ExprKind::Lit(literal) => json!({ "kind": "Literal", "value": literal_json(literal) }),
+        ExprKind::Grouping(inner) => json!({ "kind": "Grouping", "expression": expr_json(inner) }),
+        ExprKind::Unary(operator, right) => json!({ "kind": "Unary", "operator": token_json(operator), "right": expr_json(right) }),
+        ExprKind::Binary(left, operator, right) => json!({
+            "kind": "Binary", "left": expr_json(left), "operator": token_json(operator), "right": expr_json(right),
+        }),
+        ExprKind::Logical(left, operator, right) => json!({
+            "kind": "Logical", "left": expr_json(left), "operator": token_json(operator), "right": expr_json(right),
+        }),
+        ExprKind::Variable(name) => json!({ "kind": "Variable", "name": token_json(name) }),
+        ExprKind::Assign(name, value) => json!({ "kind": "Assign", "name": token_json(name), "value": expr_json(value) }),
+        ExprKind::Call(callee, paren, arguments, keywords) => json!({
+            "kind": "Call",
+            "callee": expr_json(callee),
+            "paren": token_json(paren),
+            "arguments": arguments.iter().map(expr_json).collect::<Vec<Value>>(),
+            "keywords": keywords.iter()
+                .map(|keyword| json!({ "name": token_json(&keyword.name), "value": expr_json(&keyword.value) }))
+                .collect::<Vec<Value>>(),
+        }),
+        ExprKind::Get(object, name) => json!({ "kind": "Get", "object": expr_json(object), "name": token_json(name) }),
+        ExprKind::List(elements) => json!({ "kind": "List", "elements": elements.iter().map(expr_json).collect::<Vec<Value>>() }),
+        ExprKind::Index(object, bracket, index) => json!({
+            "kind": "Index", "object": expr_json(object), "bracket": token_json(bracket), "index": expr_json(index),
+        }),
+        ExprKind::Object(properties) => json!({
+            "kind": "Object",
+            "properties": properties.iter()
+                .map(|property| json!({ "name": token_json(&property.name), "value": expr_json(&property.value) }))
+                .collect::<Vec<Value>>(),
+        }),
+        ExprKind::Destructure(pattern, value) => json!({ "kind": "Destructure", "pattern": pattern_json(pattern), "value": expr_json(value) }),
+    }
+}
+
+fn pattern_json(pattern: &Pattern) -> Value {
+    match pattern {
+        Pattern::Name(name) => json!({ "kind": "NamePattern", "name": token_json(name) }),
+        Pattern::List(bracket, elements, rest) => json!({
+            "kind": "ListPattern",
+            "bracket": token_json(bracket),
+            "elements": elements.iter().map(pattern_json).collect::<Vec<Value>>(),
+            "rest": option_json(rest.as_deref(), token_json),
+        }),
+        Pattern::Object(brace, fields) => json!({
+            "kind": "ObjectPattern",
+            "brace": token_json(brace),
+            "fields": fields.iter()
+                .map(|(key, pattern)| json!({ "key": token_json(key), "pattern": pattern_json(pattern) }))
+                .collect::<Vec<Value>>(),
+        }),
+    }
+}
+
+fn match_pattern_json(pattern: &MatchPattern) -> Value {
+    match pattern {
+        MatchPattern::Literal(literal) => json!({ "kind": "LiteralPattern", "value": literal_json(literal) }),
+        MatchPattern::Range(low, high, inclusive) => json!({ "kind": "RangePattern", "low": low, "high": high, "inclusive": inclusive }),
+        MatchPattern::Wildcard => json!({ "kind": "WildcardPattern" }),
+        MatchPattern::Binding(name) => json!({ "kind": "BindingPattern", "name": token_json(name) }),
+        MatchPattern::Alternatives(alternatives) => json!({
+            "kind": "AlternativesPattern",
+            "alternatives": alternatives.iter().map(match_pattern_json).collect::<Vec<Value>>(),
+        }),
+    }
+}
+
+fn stmt_json(stmt: &Stmt) -> Value {
+    node_json(stmt.id, stmt.span, stmt_kind_json(&stmt.kind))
+}
+
+fn stmt_kind_json(kind: &StmtKind) -> Value {
+    match kind {
+        StmtKind::ExprStmt(expr) => json!({ "kind": "Expression", "expression": expr_json(expr) }),
+        StmtKind::PrintStmt(expr) => json!({ "kind": "Print", "expression": expr_json(expr) }),
+        StmtKind::Declaration { name, initializer, constant } => json!({
+            "kind": "Var", "name": token_json(name), "initializer": option_json(initializer.as_deref(), expr_json), "constant": constant,
+        }),
+        StmtKind::Destructure { pattern, initializer, constant } => json!({
+            "kind": "VarDestructure", "pattern": pattern_json(pattern), "initializer": expr_json(initializer), "constant": constant,
+        }),
+        StmtKind::Block(stmts) => json!({ "kind": "Block", "statements": stmts.iter().map(stmt_json).collect::<Vec<Value>>() }),
+        StmtKind::Function(fun_stmt) => json!({
+            "kind": "Function",
+            "name": token_json(&fun_stmt.name),
+            "params": fun_stmt.params.iter()
+                .map(|param| json!({
+                    "name": token_json(&param.name),
+                    "constant": param.constant,
+                    "default": option_json(param.default.as_deref(), expr_json),
+                    "rest": param.rest,
+                }))
+                .collect::<Vec<Value>>(),
+            "body": fun_stmt.body.iter().map(stmt_json).collect::<Vec<Value>>(),
+        }),
+        StmtKind::If(condition, then_branch, else_branch) => json!({
+            "kind": "If",
+            "condition": expr_json(condition),
+            "then": stmt_json(then_branch),
+            "else": option_json(else_branch.as_deref(), stmt_json),
+        }),
+        StmtKind::While(condition, body) => json!({ "kind": "While", "condition": expr_json(condition), "body": stmt_json(body) }),
+        StmtKind::DoWhile(body, condition) => json!({ "kind": "DoWhile", "body": stmt_json(body), "condition": expr_json(condition) }),
+        StmtKind::ForIn(name, iterable, body) => json!({
+            "kind": "ForIn", "name": token_json(name), "iterable": expr_json(iterable), "body": stmt_json(body),
+        }),
+        StmtKind::Return(keyword, value) => json!({
+            "kind": "Return", "keyword": token_json(keyword), "value": option_json(value.as_deref(), expr_json),
+        }),
+        StmtKind::Import(import) => json!({
+            "kind": "Import",
+            "keyword": token_json(&import.keyword),
+            "path": import.path,
+            "alias": option_json(import.alias.as_ref(), token_json),
+            "names": import.names.iter().map(token_json).collect::<Vec<Value>>(),
+        }),
+        StmtKind::Match(match_stmt) => json!({
+            "kind": "Match",
+            "keyword": token_json(&match_stmt.keyword),
+            "subject": expr_json(&match_stmt.subject),
+            "arms": match_stmt.arms.iter()
+                .map(|arm| json!({
+                    "pattern": match_pattern_json(&arm.pattern),
+                    "guard": option_json(arm.guard.as_ref(), expr_json),
+                    "body": stmt_json(&arm.body),
+                }))
+                .collect::<Vec<Value>>(),
+        }),
+    }
+}
+
+// A JSON object being read back as a node, for field lookups that report
+// which node was missing what.
+struct Node<'a> {
+    kind: &'a str,
+    fields: &'a Map<String, Value>,
+}
+
+impl<'a> Node<'a> {
+    fn new(value: &'a Value) -> Result<Self, String> {
+        let fields = value.as_object().ok_or_else(|| format!("Expected a node but got {}.", value))?;
+        let kind = fields.get("kind").and_then(Value::as_str).unwrap_or("node");
+        Ok(Node { kind, fields })
+    }
+
+    fn field(&self, name: &str) -> Result<&'a Value, String> {
+        self.fields.get(name).ok_or_else(|| format!("{} is missing '{}'.", self.kind, name))
+    }
+
+    fn span(&self) -> Span {
+        let position = |name: &str| self.optional("span")
+            .and_then(|span| span.get(name))
+            .and_then(Value::as_u64)
+            .unwrap_or(0) as usize;
+
+        Span { line: position("line"), column: position("column"), end_line: position("end_line"), end_column: position("end_column") }
+    }
+
+    fn optional(&self, name: &str) -> Option<&'a Value> {
+        self.fields.get(name).filter(|value| !value.is_null())
+    }
+
+    fn array(&self, name: &str) -> Result<&'a Vec<Value>, String> {
+        self.field(name)?.as_array().ok_or_else(|| format!("{} field '{}' must be an array.", self.kind, name))
+    }
+
+    fn string(&self, name: &str) -> Result<&'a str, String> {
+        self.field(name)?.as_str().ok_or_else(|| format!("{} field '{}' must be a string.", self.kind, name))
+    }
+
+    fn number(&self, name: &str) -> Result<f64, String> {
+        self.field(name)?.as_f64().ok_or_else(|| format!("{} field '{}' must be a number.", self.kind, name))
+    }
+
+    fn boolean(&self, name: &str) -> Result<bool, String> {
+        self.field(name)?.as_bool().ok_or_else(|| format!("{} field '{}' must be a boolean.", self.kind, name))
+    }
+
+    fn token(&self, name: &str) -> Result<Token, String> {
+        token_from(self.field(name)?)
+    }
+
+    fn expr(&self, name: &str) -> Result<Expr, String> {
+        expr_from(self.field(name)?)
+    }
+
+    fn stmt(&self, name: &str) -> Result<Stmt, String> {
+        stmt_from(self.field(name)?)
+    }
+}
+
+fn token_from(value: &Value) -> Result<Token, String> {
+    let node = Node::new(value)?;
+    let lexeme = node.string("lexeme")?;
+    let line = node.optional("line").and_then(Value::as_u64).unwrap_or(0) as usize;
+    let column = node.optional("column").and_then(Value::as_u64).unwrap_or(0) as usize;
+
+    let mut scanner = Scanner::new(lexeme);
+    let tokens = scanner.scan_tokens();
+    match tokens.as_slice() {
+        [token, _eof] if token.lexeme == lexeme => Ok(Token { line, column, ..token.clone() }),
+        _ => Err(format!("'{}' is not a single token.", lexeme)),
+    }
+}
+
+fn literal_from(value: &Value) -> Result<Literal, String> {
+    match value {
+        Value::Null => Ok(Literal::Nil),
+        Value::Bool(b) => Ok(Literal::Boolean(*b)),
+        Value::Number(n) => n.as_f64().map(Literal::Number).ok_or_else(|| format!("{} is not a number Lox can hold.", n)),
+        Value::String(s) => Ok(Literal::Str(s.clone())),
+        Value::Array(items) => {
+            let items = items.iter().map(literal_from).collect::<Result<Vec<Literal>, String>>()?;
+            Ok(Literal::List(Rc::new(RefCell::new(items))))
+        }
+        Value::Object(fields) => {
+            let mut object = LoxObject::new();
+            for (name, value) in fields {
+                object.set(name.clone(), literal_from(value)?);
+            }
+            Ok(Literal::Object(Rc::new(RefCell::new(object))))
+        }
+    }
+}
+
+fn exprs_from(values: &[Value]) -> Result<Vec<Expr>, String> {
+    values.iter().map(expr_from).collect()
+}
+
+fn stmts_from(values: &[Value]) -> Result<Vec<Stmt>, String> {
+    values.iter().map(stmt_from).collect()
+}
+
+fn expr_from(value: &Value) -> Result<Expr, String> {
+    let node = Node::new(value)?;
+
+    let kind = match node.kind {
+        "Literal" => ExprKind::Lit(literal_from(node.field("value")?)?),
+        "Grouping" => ExprKind::Grouping(Box::from(node.expr("expression")?)),
+        "Unary" => ExprKind::Unary(Box::from(node.token("operator")?), Box::from(node.expr("right")?)),
+        "Binary" => ExprKind::Binary(Box::from(node.expr("left")?), Box::from(node.token("operator")?), Box::from(node.expr("right")?)),
+        "Logical" => ExprKind::Logical(Box::from(node.expr("left")?), Box::from(node.token("operator")?), Box::from(node.expr("right")?)),
+        "Variable" => ExprKind::Variable(Box::from(node.token("name")?)),
+        "Assign" => ExprKind::Assign(Box::from(node.token("name")?), Box::from(node.expr("value")?)),
+        "Call" => {
+            let keywords = node.array("keywords")?.iter()
+                .map(|keyword| {
+                    let keyword = Node::new(keyword)?;
+                    Ok(KeywordArg::new(keyword.token("name")?, keyword.expr("value")?))
+                })
+                .collect::<Result<Vec<KeywordArg>, String>>()?;
+            ExprKind::Call(
+                Box::from(node.expr("callee")?),
+                Box::from(node.token("paren")?),
+                Box::from(exprs_from(node.array("arguments")?)?),
+                Box::from(keywords),
+            )
+        }
+        "Get" => ExprKind::Get(Box::from(node.expr("object")?), Box::from(node.token("name")?)),
+        "List" => ExprKind::List(Box::from(exprs_from(node.array("elements")?)?)),
+        "Index" => ExprKind::Index(Box::from(node.expr("object")?), Box::from(node.token("bracket")?), Box::from(node.expr("index")?)),
+        "Object" => {
+            let properties = node.array("properties")?.iter()
+                .map(|property| {
+                    let property = Node::new(property)?;
+                    Ok(Property::new(property.token("name")?, property.expr("value")?))
+                })
+                .collect::<Result<Vec<Property>, String>>()?;
+            ExprKind::Object(Box::from(properties))
+        }
+        "Destructure" => ExprKind::Destructure(Box::from(pattern_from(node.field("pattern")?)?), Box::from(node.expr("value")?)),
+        kind => return Err(format!("Unknown expression kind '{}'.", kind)),
+    };
+
+    Ok(Expr::new(node.span(), kind))
+}
+
+fn pattern_from(value: &Value) -> Result<Pattern, String> {
+    let node = Node::new(value)?;
+
+    match node.kind {
+        "NamePattern" => Ok(Pattern::Name(Box::from(node.token("name")?))),
+        "ListPattern" => {
+            let elements = node.array("elements")?.iter().map(pattern_from).collect::<Result<Vec<Pattern>, String>>()?;
+            let rest = node.optional("rest").map(token_from).transpose()?.map(Box::from);
+            Ok(Pattern::List(Box::from(node.token("bracket")?), elements, rest))
+        }
+        "ObjectPattern" => {
+            let fields = node.array("fields")?.iter()
+                .map(|field| {
+                    let field = Node::new(field)?;
+                    Ok((field.token("key")?, pattern_from(field.field("pattern")?)?))
+                })
+                .collect::<Result<Vec<(Token, Pattern)>, String>>()?;
+            Ok(Pattern::Object(Box::from(node.token("brace")?), fields))
+        }
+        kind => Err(format!("Unknown pattern kind '{}'.", kind)),
+    }
+}
+
+fn match_pattern_from(value: &Value) -> Result<MatchPattern, String> {
+    let node = Node::new(value)?;
+
+    match node.kind {
+        "LiteralPattern" => Ok(MatchPattern::Literal(literal_from(node.field("value")?)?)),
+        "RangePattern" => Ok(MatchPattern::Range(node.number("low")?, node.number("high")?, node.boolean("inclusive")?)),
+        "WildcardPattern" => Ok(MatchPattern::Wildcard),
+        "BindingPattern" => Ok(MatchPattern::Binding(node.token("name")?)),
+        "AlternativesPattern" => {
+            let alternatives = node.array("alternatives")?.iter().map(match_pattern_from).collect::<Result<Vec<MatchPattern>, String>>()?;
+            Ok(MatchPattern::Alternatives(alternatives))
+        }
+        kind => Err(format!("Unknown match pattern kind '{}'.", kind)),
+    }
+}
+
+fn stmt_from(value: &Value) -> Result<Stmt, String> {
+    let node = Node::new(value)?;
+
+    let kind = match node.kind {
+        "Expression" => StmtKind::ExprStmt(Box::from(node.expr("expression")?)),
+        "Print" => StmtKind::PrintStmt(Box::from(node.expr("expression")?)),
+        "Var" => StmtKind::Declaration {
+            name: node.token("name")?,
+            initializer: node.optional("initializer").map(expr_from).transpose()?.map(Box::from),
+            constant: node.boolean("constant")?,
+        },
+        "VarDestructure" => StmtKind::Destructure {
+            pattern: Box::from(pattern_from(node.field("pattern")?)?),
+            initializer: Box::from(node.expr("initializer")?),
+            constant: node.boolean("constant")?,
+        },
+        "Block" => StmtKind::Block(Box::from(stmts_from(node.array("statements")?)?)),
+        "Function" => {
+            let params = node.array("params")?.iter()
+                .map(|param| {
+                    let param = Node::new(param)?;
+                    let default = param.optional("default").map(expr_from).transpose()?.map(Box::from);
+                    Ok(Param::new(param.token("name")?, param.boolean("constant")?, default, param.boolean("rest")?))
+                })
+                .collect::<Result<Vec<Param>, String>>()?;
+            let body = stmts_from(node.array("body")?)?;
+            StmtKind::Function(Box::from(FunctionStmt::new(node.token("name")?, params, body)))
+        }
+        "If" => StmtKind::If(
+            Box::from(node.expr("condition")?),
+            Box::from(node.stmt("then")?),
+            node.optional("else").map(stmt_from).transpose()?.map(Box::from),
+        ),
+        "While" => StmtKind::While(Box::from(node.expr("condition")?), Box::from(node.stmt("body")?)),
+        "DoWhile" => StmtKind::DoWhile(Box::from(node.stmt("body")?), Box::from(node.expr("condition")?)),
+        "ForIn" => StmtKind::ForIn(Box::from(node.token("name")?), Box::from(node.expr("iterable")?), Box::from(node.stmt("body")?)),
+        "Return" => StmtKind::Return(
+            Box::from(node.token("keyword")?),
+            node.optional("value").map(expr_from).transpose()?.map(Box::from),
+        ),
+        "Import" => {
+            let alias = node.optional("alias").map(token_from).transpose()?;
+            let names = node.array("names")?.iter().map(token_from).collect::<Result<Vec<Token>, String>>()?;
+            StmtKind::Import(Box::from(ImportStmt::new(node.token("keyword")?, node.string("path")?.to_string(), alias, names)))
+        }
+        "Match" => {
+            let arms = node.array("arms")?.iter()
+                .map(|arm| {
+                    let arm = Node::new(arm)?;
+                    let guard = arm.optional("guard").map(expr_from).transpose()?;
+                    Ok(MatchArm::new(match_pattern_from(arm.field("pattern")?)?, guard, arm.stmt("body")?))
+                })
+                .collect::<Result<Vec<MatchArm>, String>>()?;
+            StmtKind::Match(Box::from(MatchStmt::new(node.token("keyword")?, node.expr("subject")?, arms)))
+        }
+        kind => return Err(format!("Unknown statement kind '{}'.", kind)),
+    };
+
+    Ok(Stmt::new(node.span(), kind))
+}
+
+// Walks a loaded tree the way the parser reads source, with the same scopes.
+struct Checks {
+    constants: Constants,
+    top_level: bool,
+}
+
+impl Checks {
+    fn scoped(&mut self, body: impl FnOnce(&mut Self) -> Result<(), String>) -> Result<(), String> {
+        let top_level = std::mem::replace(&mut self.top_level, false);
+        self.constants.begin_scope();
+        let result = body(self);
+        self.constants.end_scope();
+        self.top_level = top_level;
+        result
+    }
+
+    fn statements(&mut self, stmts: &[Stmt]) -> Result<(), String> {
+        stmts.iter().try_for_each(|stmt| self.statement(stmt))
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), String> {
+        match &stmt.kind {
+            StmtKind::ExprStmt(expr) | StmtKind::PrintStmt(expr) => self.expression(expr),
+            StmtKind::Declaration { name, initializer, constant } => {
+                match initializer {
+                    Some(initializer) => self.expression(initializer)?,
+                    None if *constant => return Err(format!("[line {}] Error at '{}': Constant '{}' must be initialized.", name.line, name.lexeme, name.lexeme)),
+                    None => {}
+                }
+                self.constants.declare(name, *constant)
+            }
+            StmtKind::Destructure { pattern, initializer, constant } => {
+                check_pattern(pattern)?;
+                self.expression(initializer)?;
+                pattern.names().into_iter().try_for_each(|name| self.constants.declare(name, *constant))
+            }
+            StmtKind::Block(stmts) => self.scoped(|checks| checks.statements(stmts)),
+            StmtKind::Function(fun_stmt) => {
+                self.constants.declare(&fun_stmt.name, false)?;
+                self.scoped(|checks| {
+                    for (i, param) in fun_stmt.params.iter().enumerate() {
+                        check_parameter(&fun_stmt.params[..i], param)?;
+                        if let Some(default) = &param.default {
+                            checks.expression(default)?;
+                        }
+                        checks.constants.declare(&param.name, param.constant)?;
+                    }
+                    checks.scoped(|checks| checks.statements(&fun_stmt.body))
+                })
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.expression(condition)?;
+                self.statement(then_branch)?;
+                else_branch.iter().try_for_each(|else_branch| self.statement(else_branch))
+            }
+            StmtKind::While(condition, body) | StmtKind::DoWhile(body, condition) => {
+                self.expression(condition)?;
+                self.statement(body)
+            }
+            StmtKind::ForIn(name, iterable, body) => self.scoped(|checks| {
+                checks.expression(iterable)?;
+                checks.constants.declare(name, false)?;
+                checks.statement(body)
+            }),
+            StmtKind::Return(_, value) => value.iter().try_for_each(|value| self.expression(value)),
+            StmtKind::Import(import) => {
+                if !self.top_level {
+                    return Err(format!("[line {}] Error at 'import': Import must be at top level.", import.keyword.line));
+                }
+                import.alias.iter().chain(import.names.iter()).try_for_each(|name| self.constants.declare(name, false))
+            }
+            StmtKind::Match(match_stmt) => {
+                self.expression(&match_stmt.subject)?;
+                for arm in &match_stmt.arms {
+                    if let MatchPattern::Alternatives(alternatives) = &arm.pattern {
+                        if let Some(MatchPattern::Binding(name)) = alternatives.iter().find(|alternative| matches!(alternative, MatchPattern::Binding(_))) {
+                            return Err(format!("[line {}] Error at '{}': Can't bind a name inside alternative patterns.", name.line, name.lexeme));
+                        }
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard)?;
+                    }
+                    self.scoped(|checks| {
+                        if let MatchPattern::Binding(name) = &arm.pattern {
+                            checks.constants.declare(name, false)?;
+                        }
+                        checks.statement(&arm.body)
+                    })?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Result<(), String> {
+        match &expr.kind {
+            ExprKind::Lit(_) | ExprKind::Variable(_) => Ok(()),
+            ExprKind::Grouping(inner) | ExprKind::Unary(_, inner) | ExprKind::Get(inner, _) => self.expression(inner),
+            ExprKind::Binary(left, _, right) | ExprKind::Logical(left, _, right) | ExprKind::Index(left, _, right) => {
+                self.expression(left)?;
+                self.expression(right)
+            }
+            ExprKind::Assign(name, value) => {
+                self.expression(value)?;
+                self.constants.assign(name)
+            }
+            ExprKind::Destructure(pattern, value) => {
+                pattern.names().into_iter().try_for_each(|name| self.constants.assign(name))?;
+                self.expression(value)
+            }
+            ExprKind::Call(callee, paren, arguments, keywords) => {
+                if arguments.len() + keywords.len() > 255 {
+                    return Err(format!("[line {}] Error at '{}': Can't have more than 255 arguments.", paren.line, paren.lexeme));
+                }
+                for (i, keyword) in keywords.iter().enumerate() {
+                    if keywords[..i].iter().any(|seen| seen.name.lexeme == keyword.name.lexeme) {
+                        return Err(format!("[line {}] Error at '{}': Duplicate keyword argument '{}'.", keyword.name.line, keyword.name.lexeme, keyword.name.lexeme));
+                    }
+                }
+                self.expression(callee)?;
+                arguments.iter().try_for_each(|argument| self.expression(argument))?;
+                keywords.iter().try_for_each(|keyword| self.expression(&keyword.value))
+            }
+            ExprKind::List(elements) => elements.iter().try_for_each(|element| self.expression(element)),
+            ExprKind::Object(properties) => properties.iter().try_for_each(|property| self.expression(&property.value)),
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use std::{collections::HashMap, fmt::Write};
+
+use serde_json::{json, Value};
+
+use crate::{evaluator::Evaluator, expr::{Expr, ExprKind, Literal, LoxCallables, NodeId}, scopes::Scopes, stmt::{FunctionStmt, MatchPattern, Stmt, StmtKind}, unparser};
+
+/// Who calls whom in a program, worked out without running it.
+///
+/// A callee is resolved when it is a plain name that, following the same
+/// scoping the resolver uses, refers to a `fun` declaration or a native
+/// function. Anything else, like a parameter, a property or the result of
+/// another call, is kept as an unresolved call.
+pub struct CallGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+#[derive(PartialEq)]
+enum NodeKind {
+    Script,
+    Function,
+    Native,
+    Unresolved,
+}
+
+struct Node {
+    kind: NodeKind,
+    // Nested functions are named through the functions around them, as `outer.inner`.
+    name: String,
+    line: Option<usize>,
+    recursive: bool,
+}
+
+struct Edge {
+    from: usize,
+    to: usize,
+    lines: Vec<usize>,
+    cycle: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Binding {
+    Function(usize),
+    Native(usize),
+    Other,
+}
+
+const SCRIPT: usize = 0;
+
+impl CallGraph {
+    pub fn new(stmts: &[Stmt]) -> Self {
+        let mut builder = Builder {
+            graph: CallGraph { nodes: Vec::new(), edges: Vec::new() },
+            names: Scopes::new(Binding::Other),
+            declared: HashMap::new(),
+            unresolved: HashMap::new(),
+            current: SCRIPT,
+        };
+        builder.graph.add_node(NodeKind::Script, "<script>".to_string(), None);
+        builder.natives();
+        let Builder { graph, names, declared, .. } = &mut builder;
+        names.top_level(stmts, |stmt, fun_stmt| {
+            let node = graph.add_node(NodeKind::Function, fun_stmt.name.lexeme.clone(), Some(fun_stmt.name.line));
+            declared.insert(stmt.id, node);
+            Binding::Function(node)
+        });
+        builder.statements(stmts);
+
+        let mut graph = builder.graph;
+        graph.mark_cycles();
+        graph
+    }
+
+    fn add_node(&mut self, kind: NodeKind, name: String, line: Option<usize>) -> usize {
+        self.nodes.push(Node { kind, name, line, recursive: false });
+        self.nodes.len() - 1
+    }
+
+    fn node_id(&self, index: usize) -> String {
+        match self.nodes[index].kind {
+            NodeKind::Script => "script".to_string(),
+            NodeKind::Function => format!("f{}", index),
+            NodeKind::Native => format!("native_{}", self.nodes[index].name),
+            NodeKind::Unresolved => format!("unresolved{}", index),
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph calls {\n".to_string();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let name = node.name.replace('\\', "\\\\").replace('"', "\\\"");
+            let attributes = match node.kind {
+                NodeKind::Script => format!("label=\"{}\", shape=box", name),
+                NodeKind::Function => {
+                    let line = node.line.map(|line| format!("\\nline {}", line)).unwrap_or_default();
+                    let color = if node.recursive { ", color=red" } else { "" };
+                    format!("label=\"{}{}\"{}", name, line, color)
+                }
+                NodeKind::Native => format!("label=\"{}\", shape=box, style=dashed", name),
+                NodeKind::Unresolved => format!("label=\"{}\", shape=plaintext, fontcolor=gray", name),
+            };
+            writeln!(dot, "    {} [{}];", self.node_id(index), attributes).unwrap();
+        }
+        for edge in &self.edges {
+            let mut attributes = Vec::new();
+            if edge.cycle {
+                attributes.push("color=red".to_string());
+            }
+            if self.nodes[edge.to].kind == NodeKind::Unresolved {
+                attributes.push("style=dotted".to_string());
+            }
+            let attributes = if attributes.is_empty() { String::new() } else { format!(" [{}]", attributes.join(", ")) };
+            writeln!(dot, "    {} -> {}{};", self.node_id(edge.from), self.node_id(edge.to), attributes).unwrap();
+        }
+        dot.push('}');
+        dot
+    }
+
+    pub fn to_json(&self) -> Value {
+        let nodes: Vec<Value> = self.nodes.iter().enumerate()
+            .map(|(index, node)| {
+                let kind = match node.kind {
+                    NodeKind::Script => "script",
+                    NodeKind::Function => "function",
+                    NodeKind::Native => "native",
+                    NodeKind::Unresolved => "unresolved",
+                };
+                let mut value = json!({ "id": self.node_id(index), "kind": kind, "name": node.name });
+                if node.kind == NodeKind::Function {
+                    value["line"] = json!(node.line);
+                    value["recursive"] = json!(node.recursive);
+                }
+                value
+            })
+            .collect();
+        let edges: Vec<Value> = self.edges.iter()
+            .map(|edge| json!({
+                "from": self.node_id(edge.from),
+                "to": self.node_id(edge.to),
+                "lines": edge.lines,
+                "cycle": edge.cycle,
+            }))
+            .collect();
+        json!({ "nodes": nodes, "edges": edges })
+    }
+
+    // Marks the functions that can reach themselves again, and the calls that
+    // close those loops, from the graph's strongly connected components.
+    fn mark_cycles(&mut self) {
+        let mut successors = vec![Vec::new(); self.nodes.len()];
+        for edge in &self.edges {
+            successors[edge.from].push(edge.to);
+        }
+
+        let mut tarjan = Tarjan::new(&successors);
+        for node in 0..self.nodes.len() {
+            if tarjan.index[node].is_none() {
+                tarjan.visit(node);
+            }
+        }
+
+        let component = tarjan.component;
+        for edge in self.edges.iter_mut() {
+            edge.cycle = component[edge.from] == component[edge.to];
+            if edge.cycle {
+                self.nodes[edge.from].recursive = true;
+                self.nodes[edge.to].recursive = true;
+            }
+        }
+    }
+}
+
+// Tarjan's algorithm, numbering each node's strongly connected component.
+struct Tarjan<'a> {
+    successors: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    next: usize,
+    component: Vec<usize>,
+    components: usize,
+}
+
+impl<'a> Tarjan<'a> {
+    fn new(successors: &'a [Vec<usize>]) -> Self {
+        let count = successors.len();
+        Tarjan {
+            successors,
+            index: vec![None; count],
+            low: vec![0; count],
+            stack: Vec::new(),
+            on_stack: vec![false; count],
+            next: 0,
+            component: vec![0; count],
+            components: 0,
+        }
+    }
+
+    fn visit(&mut self, node: usize) {
+        self.index[node] = Some(self.next);
+        self.low[node] = self.next;
+        self.next += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for &successor in &self.successors[node] {
+            match self.index[successor] {
+                None => {
+                    self.visit(successor);
+                    self.low[node] = self.low[node].min(self.low[successor]);
+                }
+                Some(index) if self.on_stack[successor] => self.low[node] = self.low[node].min(index),
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low[node]) == self.index[node] {
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                self.component[member] = self.components;
+                if member == node {
+                    break;
+                }
+            }
+            self.components += 1;
+        }
+    }
+}
+
+struct Builder {
+    graph: CallGraph,
+    names: Scopes<Binding>,
+    declared: HashMap<NodeId, usize>,
+    unresolved: HashMap<String, usize>,
+    current: usize,
+}
+
+impl Builder {
+    fn add_edge(&mut self, to: usize, line: usize) {
+        let from = self.current;
+        match self.graph.edges.iter_mut().find(|edge| edge.from == from && edge.to == to) {
+            Some(edge) => edge.lines.push(line),
+            None => self.graph.edges.push(Edge { from, to, lines: vec![line], cycle: false }),
+        }
+    }
+
+    // Whatever a fresh interpreter defines natively, like `clock`.
+    fn natives(&mut self) {
+        let evaluator = Evaluator::new();
+        for (name, value, _) in evaluator.builtins.borrow().bindings() {
+            if let Some(Literal::LoxCallable(LoxCallables::LoxAnonymous(_))) = value.as_deref() {
+                let node = self.graph.add_node(NodeKind::Native, name.clone(), None);
+                self.names.global(&name, Binding::Native(node));
+            }
+        }
+    }
+
+    fn statements(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn scoped(&mut self, stmts: &[Stmt]) {
+        self.names.begin_scope();
+        self.statements(stmts);
+        self.names.end_scope();
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::ExprStmt(expr) | StmtKind::PrintStmt(expr) => self.expression(expr),
+            StmtKind::Declaration { name, initializer, .. } => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.names.declare(&name.lexeme, Binding::Other);
+            }
+            StmtKind::Destructure { pattern, initializer, .. } => {
+                self.expression(initializer);
+                for name in pattern.names() {
+                    self.names.declare(&name.lexeme, Binding::Other);
+                }
+            }
+            StmtKind::Block(stmts) => self.scoped(stmts),
+            StmtKind::Function(fun_stmt) => {
+                let node = match self.declared.get(&stmt.id) {
+                    Some(node) => *node,
+                    None => {
+                        let name = match self.current {
+                            SCRIPT => fun_stmt.name.lexeme.clone(),
+                            outer => format!("{}.{}", self.graph.nodes[outer].name, fun_stmt.name.lexeme),
+                        };
+                        self.graph.add_node(NodeKind::Function, name, Some(fun_stmt.name.line))
+                    }
+                };
+                self.names.declare(&fun_stmt.name.lexeme, Binding::Function(node));
+                self.function(fun_stmt, node);
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::While(condition, body) | StmtKind::DoWhile(body, condition) => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            StmtKind::ForIn(name, iterable, body) => {
+                self.expression(iterable);
+                self.names.begin_scope();
+                self.names.declare(&name.lexeme, Binding::Other);
+                self.statement(body);
+                self.names.end_scope();
+            }
+            StmtKind::Return(_, value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            StmtKind::Import(_) => {}
+            StmtKind::Match(match_stmt) => {
+                self.expression(&match_stmt.subject);
+                for arm in &match_stmt.arms {
+                    self.names.begin_scope();
+                    if let MatchPattern::Binding(name) = &arm.pattern {
+                        self.names.declare(&name.lexeme, Binding::Other);
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.statement(&arm.body);
+                    self.names.end_scope();
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, fun_stmt: &FunctionStmt, node: usize) {
+        let caller = std::mem::replace(&mut self.current, node);
+        self.names.begin_scope();
+        for param in &fun_stmt.params {
+            if let Some(default) = &param.default {
+                self.expression(default);
+            }
+            self.names.declare(&param.name.lexeme, Binding::Other);
+        }
+        self.statements(&fun_stmt.body);
+        self.names.end_scope();
+        self.current = caller;
+    }
+
+    fn call(&mut self, callee: &Expr, line: usize) {
+        let binding = match &callee.kind {
+            ExprKind::Variable(name) => self.names.lookup(&name.lexeme),
+            _ => None,
+        };
+        let to = match binding {
+            Some(Binding::Function(node) | Binding::Native(node)) => node,
+            _ => {
+                let source = unparser::expression(callee);
+                match self.unresolved.get(&source) {
+                    Some(node) => *node,
+                    None => {
+                        let node = self.graph.add_node(NodeKind::Unresolved, source.clone(), None);
+                        self.unresolved.insert(source, node);
+                        node
+                    }
+                }
+            }
+        };
+        self.add_edge(to, line);
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lit(_) | ExprKind::Variable(_) => {}
+            ExprKind::Grouping(inner) | ExprKind::Unary(_, inner) => self.expression(inner),
+            ExprKind::Binary(left, _, right) | ExprKind::Logical(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExprKind::Assign(_, value) | ExprKind::Destructure(_, value) => self.expression(value),
+            ExprKind::Call(callee, paren, arguments, keywords) => {
+                self.expression(callee);
+                for argument in arguments.iter() {
+                    self.expression(argument);
+                }
+                for keyword in keywords.iter() {
+                    self.expression(&keyword.value);
+                }
+                self.call(callee, paren.line);
+            }
+            ExprKind::Get(object, _) => self.expression(object),
+            ExprKind::Index(object, _, index) => {
+                self.expression(object);
+                self.expression(index);
+            }
+            ExprKind::List(elements) => {
+                for element in elements.iter() {
+                    self.expression(element);
+                }
+            }
+            ExprKind::Object(properties) => {
+                for property in properties.iter() {
+                    self.expression(&property.value);
+                }
+            }
+        }
+    }
+}
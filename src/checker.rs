@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+
+use crate::{environment::Environment, evaluator::arity_message, expr::{Expr, ExprKind, Literal, LoxCallables}, lox_callable::LoxCallable, scopes::Scopes, stmt::{FunctionStmt, MatchPattern, Stmt, StmtKind}, token::Token};
+
+/// Warns about calls that will fail if they run: calls with the wrong
+/// arguments to a function or native the callee name can only refer to, and
+/// calls of values that can't be called at all, like `3()`. These are
+/// warnings because the call may sit in code that never runs.
+///
+/// Names are resolved the way the resolver does, with `globals` standing in
+/// for whatever the program doesn't declare itself. A name that is assigned
+/// to anywhere in the program could hold anything, so its calls aren't checked.
+pub fn check(stmts: &[Stmt], globals: &Environment) -> Vec<String> {
+    let mut reassigned = HashSet::new();
+    for stmt in stmts {
+        assignments(stmt, &mut reassigned);
+    }
+
+    let mut checker = Checker { names: Scopes::new(Binding::Other), reassigned, warnings: Vec::new() };
+    checker.natives(globals);
+    checker.names.top_level(stmts, |_, fun_stmt| Binding::Function(fun_stmt));
+    for stmt in stmts {
+        checker.statement(stmt);
+    }
+    checker.warnings
+}
+
+#[derive(Clone, Copy)]
+enum Binding<'a> {
+    Function(&'a FunctionStmt),
+    Native(usize),
+    Other,
+}
+
+struct Checker<'a> {
+    names: Scopes<Binding<'a>>,
+    reassigned: HashSet<String>,
+    warnings: Vec<String>,
+}
+
+impl<'a> Checker<'a> {
+    fn warning(&mut self, token: &Token, message: &str) {
+        self.warnings.push(format!("[line {}] Warning at '{}': {}", token.line, token.lexeme, message));
+    }
+
+    fn natives(&mut self, globals: &Environment) {
+        for (name, value, _) in globals.visible() {
+            if let Some(Literal::LoxCallable(callable @ LoxCallables::LoxAnonymous(_))) = value.as_deref() {
+                self.names.global(&name, Binding::Native(callable.arrity()));
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, binding: Binding<'a>) {
+        self.names.declare(&name.lexeme, binding);
+    }
+
+    fn lookup(&self, name: &str) -> Binding<'a> {
+        if self.reassigned.contains(name) {
+            return Binding::Other;
+        }
+        self.names.lookup(name).unwrap_or(Binding::Other)
+    }
+
+    fn scoped(&mut self, declare: Option<&Token>, body: impl FnOnce(&mut Self)) {
+        self.names.begin_scope();
+        if let Some(name) = declare {
+            self.declare(name, Binding::Other);
+        }
+        body(self);
+        self.names.end_scope();
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::ExprStmt(expr) | StmtKind::PrintStmt(expr) => self.expression(expr),
+            StmtKind::Declaration { name, initializer, .. } => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.declare(name, Binding::Other);
+            }
+            StmtKind::Destructure { pattern, initializer, .. } => {
+                self.expression(initializer);
+                for name in pattern.names() {
+                    self.declare(name, Binding::Other);
+                }
+            }
+            StmtKind::Block(stmts) => self.scoped(None, |checker| {
+                for stmt in stmts.iter() {
+                    checker.statement(stmt);
+                }
+            }),
+            StmtKind::Function(fun_stmt) => {
+                self.declare(&fun_stmt.name, Binding::Function(fun_stmt));
+                self.scoped(None, |checker| {
+                    for param in &fun_stmt.params {
+                        if let Some(default) = &param.default {
+                            checker.expression(default);
+                        }
+                        checker.declare(&param.name, Binding::Other);
+                    }
+                    for stmt in &fun_stmt.body {
+                        checker.statement(stmt);
+                    }
+                });
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::While(condition, body) | StmtKind::DoWhile(body, condition) => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            StmtKind::ForIn(name, iterable, body) => {
+                self.expression(iterable);
+                self.scoped(Some(name), |checker| checker.statement(body));
+            }
+            StmtKind::Return(_, value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            StmtKind::Import(_) => {}
+            StmtKind::Match(match_stmt) => {
+                self.expression(&match_stmt.subject);
+                for arm in &match_stmt.arms {
+                    let binding = match &arm.pattern {
+                        MatchPattern::Binding(name) => Some(name),
+                        _ => None,
+                    };
+                    self.scoped(binding, |checker| {
+                        if let Some(guard) = &arm.guard {
+                            checker.expression(guard);
+                        }
+                        checker.statement(&arm.body);
+                    });
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, mut callee: &Expr, paren: &Token, arguments: usize, keywords: Vec<&Token>) {
+        while let ExprKind::Grouping(inner) = &callee.kind {
+            callee = inner;
+        }
+        let name = match &callee.kind {
+            ExprKind::Variable(name) => name,
+            ExprKind::Lit(Literal::LoxCallable(_)) => return,
+            ExprKind::Lit(_) | ExprKind::List(_) | ExprKind::Object(_) => {
+                self.warning(paren, "Can only call functions.");
+                return;
+            }
+            _ => return,
+        };
+
+        let (min, max, declaration) = match self.lookup(&name.lexeme) {
+            Binding::Function(fun_stmt) => (fun_stmt.arity(), fun_stmt.max_arity(), Some(fun_stmt)),
+            Binding::Native(arity) => (arity, Some(arity), None),
+            Binding::Other => return,
+        };
+
+        // The same checks, in the same order, as a call makes at run time.
+        if (keywords.is_empty() && arguments < min) || max.is_some_and(|max| arguments > max) {
+            self.warning(name, &arity_message(min, max, arguments));
+            return;
+        }
+        let Some(fun_stmt) = declaration else {
+            if let Some(keyword) = keywords.first() {
+                self.warning(keyword, "Native functions don't accept keyword arguments.");
+            }
+            return;
+        };
+        if let Err((keyword, message)) = fun_stmt.check_keywords(arguments, &keywords) {
+            self.warning(keyword, &message);
+            return;
+        }
+
+        let given = |index: usize, param: &Token| index < arguments || keywords.iter().any(|keyword| keyword.lexeme == param.lexeme);
+        let missing = fun_stmt.params.iter().enumerate()
+            .find(|(index, param)| !param.rest && param.default.is_none() && !given(*index, &param.name));
+        if let (Some((_, param)), Some(call_site)) = (missing, keywords.first()) {
+            self.warning(call_site, &format!("Missing argument for parameter '{}'.", param.name.lexeme));
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lit(_) | ExprKind::Variable(_) => {}
+            ExprKind::Grouping(inner) | ExprKind::Unary(_, inner) => self.expression(inner),
+            ExprKind::Binary(left, _, right) | ExprKind::Logical(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExprKind::Assign(_, value) | ExprKind::Destructure(_, value) => self.expression(value),
+            ExprKind::Call(callee, paren, arguments, keywords) => {
+                self.expression(callee);
+                for argument in arguments.iter() {
+                    self.expression(argument);
+                }
+                for keyword in keywords.iter() {
+                    self.expression(&keyword.value);
+                }
+                self.call(callee, paren, arguments.len(), keywords.iter().map(|keyword| &keyword.name).collect());
+            }
+            ExprKind::Get(object, _) => self.expression(object),
+            ExprKind::Index(object, _, index) => {
+                self.expression(object);
+                self.expression(index);
+            }
+            ExprKind::List(elements) => {
+                for element in elements.iter() {
+                    self.expression(element);
+                }
+            }
+            ExprKind::Object(properties) => {
+                for property in properties.iter() {
+                    self.expression(&property.value);
+                }
+            }
+        }
+    }
+}
+
+// Every name the program assigns to after declaring it.
+fn assignments(stmt: &Stmt, names: &mut HashSet<String>) {
+    let mut expression = |expr: &Expr| assigned_in(expr, names);
+    match &stmt.kind {
+        StmtKind::ExprStmt(expr) | StmtKind::PrintStmt(expr) => expression(expr),
+        StmtKind::Declaration { initializer: Some(expr), .. } | StmtKind::Destructure { initializer: expr, .. } | StmtKind::Return(_, Some(expr)) => expression(expr),
+        StmtKind::Declaration { .. } | StmtKind::Return(..) | StmtKind::Import(_) => {}
+        StmtKind::Block(stmts) => stmts.iter().for_each(|stmt| assignments(stmt, names)),
+        StmtKind::Function(fun_stmt) => {
+            for default in fun_stmt.params.iter().filter_map(|param| param.default.as_ref()) {
+                expression(default);
+            }
+            fun_stmt.body.iter().for_each(|stmt| assignments(stmt, names));
+        }
+        StmtKind::If(condition, then_branch, else_branch) => {
+            expression(condition);
+            assignments(then_branch, names);
+            if let Some(else_branch) = else_branch {
+                assignments(else_branch, names);
+            }
+        }
+        StmtKind::While(condition, body) | StmtKind::DoWhile(body, condition) | StmtKind::ForIn(_, condition, body) => {
+            expression(condition);
+            assignments(body, names);
+        }
+        StmtKind::Match(match_stmt) => {
+            expression(&match_stmt.subject);
+            for arm in &match_stmt.arms {
+                if let Some(guard) = &arm.guard {
+                    assigned_in(guard, names);
+                }
+                assignments(&arm.body, names);
+            }
+        }
+    }
+}
+
+fn assigned_in(expr: &Expr, names: &mut HashSet<String>) {
+    match &expr.kind {
+        ExprKind::Lit(_) | ExprKind::Variable(_) => {}
+        ExprKind::Assign(name, value) => {
+            names.insert(name.lexeme.clone());
+            assigned_in(value, names);
+        }
+        ExprKind::Destructure(pattern, value) => {
+            names.extend(pattern.names().into_iter().map(|name| name.lexeme.clone()));
+            assigned_in(value, names);
+        }
+        ExprKind::Grouping(inner) | ExprKind::Unary(_, inner) | ExprKind::Get(inner, _) => assigned_in(inner, names),
+        ExprKind::Binary(left, _, right) | ExprKind::Logical(left, _, right) | ExprKind::Index(left, _, right) => {
+            assigned_in(left, names);
+            assigned_in(right, names);
+        }
+        ExprKind::Call(callee, _, arguments, keywords) => {
+            assigned_in(callee, names);
+            arguments.iter().for_each(|argument| assigned_in(argument, names));
+            keywords.iter().for_each(|keyword| assigned_in(&keyword.value, names));
+        }
+        ExprKind::List(elements) => elements.iter().for_each(|element| assigned_in(element, names)),
+        ExprKind::Object(properties) => properties.iter().for_each(|property| assigned_in(&property.value, names)),
+    }
+}
@@ -1,50 +1,182 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, rc::Rc};
 
 
-use crate::{evaluator::{RuntimeError, RuntimeException}, expr::Literal, token::Token};
+use crate::{evaluator::{RuntimeError, RuntimeException}, expr::Literal, resolver::Local, token::Token};
 
+/// One scope of variables.
+///
+/// The top level of a script or module keeps its variables by name, since
+/// importers and the REPL look them up that way. Every other scope is local:
+/// its variables sit in slots numbered in declaration order, the same numbers
+/// the resolver gave them, and are read with `get_at`/`assign_at`.
 #[derive(Clone, PartialEq, Debug)]
 pub struct Environment {
     values: HashMap<String, Option<Box<Literal>>>,
+    constants: HashSet<String>,
+    slots: Vec<Slot>,
+    local: bool,
     enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
+#[derive(Clone, PartialEq, Debug)]
+struct Slot {
+    value: Option<Box<Literal>>,
+    constant: bool,
+}
+
 impl Environment {
     pub fn define (&mut self, name: String, b: Option<Box<Literal>>) {
+        if self.local {
+            self.slots.push(Slot { value: b, constant: false });
+            return;
+        }
+        self.constants.remove(&name);
         self.values.insert(name, b);
     }
 
+    pub fn define_const(&mut self, name: String, b: Option<Box<Literal>>) {
+        if self.local {
+            self.slots.push(Slot { value: b, constant: true });
+            return;
+        }
+        self.constants.insert(name.clone());
+        self.values.insert(name, b);
+    }
+
+    /// Declares `name` in this scope for a statement of the program, which
+    /// can declare over anything but one of the scope's constants.
+    pub fn declare(&mut self, name: &Token, b: Option<Box<Literal>>, constant: bool) -> Result<(), RuntimeException> {
+        if !self.local && self.constants.contains(&name.lexeme) {
+            return Err(RuntimeException::RuntimeError(RuntimeError::new(name, format!("Cannot redeclare constant '{}'.", name.lexeme).as_str())));
+        }
+        if constant {
+            self.define_const(name.lexeme.clone(), b);
+        }
+        else {
+            self.define(name.lexeme.clone(), b);
+        }
+        Ok(())
+    }
+
+    /// Looks `name` up in the nearest scope that keeps names, then outwards.
     pub fn get(&self, name: &Token) -> Result<Option<Box<Literal>>, RuntimeException> {
         if let Some(val) = self.values.get(&name.lexeme) {
             return Ok(val.clone());
         }
 
         if let Some(helper) = &self.enclosing {
-            return helper.borrow().get(name).clone();
+            return helper.borrow().get(name);
+        }
+
+        Err(undefined_variable(name))
+    }
+
+    pub fn get_at(env: &Rc<RefCell<Environment>>, local: Local, name: &Token) -> Result<Option<Box<Literal>>, RuntimeException> {
+        // Most reads are of the innermost scope, which needs no walk.
+        let value = match local.depth {
+            0 => env.borrow().slots.get(local.slot).map(|slot| slot.value.clone()),
+            depth => Environment::ancestor(env, depth).borrow().slots.get(local.slot).map(|slot| slot.value.clone()),
+        };
+        value.ok_or_else(|| undefined_variable(name))
+    }
+
+    /// This scope's own bindings sorted by name, each with whether it is const.
+    pub fn bindings(&self) -> Vec<(String, Option<Box<Literal>>, bool)> {
+        let mut bindings: Vec<_> = self.values.iter()
+            .map(|(name, value)| (name.clone(), value.clone(), self.constants.contains(name)))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    /// Every binding this scope can see, sorted by name, where one of its own
+    /// hides an enclosing scope's binding of the same name.
+    pub fn visible(&self) -> Vec<(String, Option<Box<Literal>>, bool)> {
+        let mut bindings: HashMap<String, (Option<Box<Literal>>, bool)> = match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().visible().into_iter().map(|(name, value, constant)| (name, (value, constant))).collect(),
+            None => HashMap::new(),
+        };
+        for (name, value, constant) in self.bindings() {
+            bindings.insert(name, (value, constant));
         }
+        let mut bindings: Vec<_> = bindings.into_iter().map(|(name, (value, constant))| (name, value, constant)).collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
 
-        return Err(RuntimeException::RuntimeError(RuntimeError::new(name, format!("Undefined variable '{}'", name.lexeme).as_str())));    }
+    pub fn get_own(&self, name: &str) -> Option<Box<Literal>> {
+        self.values.get(name).cloned().flatten()
+    }
 
-    pub fn assign(&mut self, name: &Token, value: Option<&Box<Literal>>) -> Result<(), RuntimeException>{
+    pub fn assign(&mut self, name: &Token, value: Option<&Literal>) -> Result<(), RuntimeException>{
 
         if self.values.contains_key(&name.lexeme) {
-            self.values.insert(name.lexeme.clone(), value.cloned());
+            if self.constants.contains(&name.lexeme) {
+                return Err(RuntimeException::RuntimeError(RuntimeError::new(name, format!("Cannot assign to constant '{}'.", name.lexeme).as_str())));
+            }
+            self.values.insert(name.lexeme.clone(), value.map(|value| Box::new(value.clone())));
             return Ok(());
         }
 
         if let Some(helper) = &self.enclosing {
-            helper.borrow_mut().assign(&name, value)?;
+            helper.borrow_mut().assign(name, value)?;
             return Ok(());
         }
 
-        return Err(RuntimeException::RuntimeError(RuntimeError::new(name, format!("Undefined variable '{}'.", name.lexeme).as_str())));
+        Err(undefined_variable(name))
+    }
+
+    pub fn assign_at(env: &Rc<RefCell<Environment>>, local: Local, name: &Token, value: Option<&Literal>) -> Result<(), RuntimeException> {
+        let scope = Environment::ancestor(env, local.depth);
+        let mut scope = scope.borrow_mut();
+        match scope.slots.get_mut(local.slot) {
+            Some(slot) if slot.constant => {
+                Err(RuntimeException::RuntimeError(RuntimeError::new(name, format!("Cannot assign to constant '{}'.", name.lexeme).as_str())))
+            }
+            Some(slot) => {
+                slot.value = value.map(|value| Box::new(value.clone()));
+                Ok(())
+            }
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    /// The environment `depth` steps out from `env`, stopping at the outermost.
+    pub fn ancestor(env: &Rc<RefCell<Environment>>, depth: usize) -> Rc<RefCell<Environment>> {
+        let mut current = env.clone();
+        for _ in 0..depth {
+            let enclosing = current.borrow().enclosing.clone();
+            match enclosing {
+                Some(enclosing) => current = enclosing,
+                None => break,
+            }
+        }
+        current
     }
 
+    /// A top-level scope, which keeps its variables by name.
     pub fn new(enclosing: Option<Rc<RefCell<Environment>>>) -> Self {
         Environment {
             values: HashMap::new(),
+            constants: HashSet::new(),
+            slots: Vec::new(),
+            local: false,
             enclosing: enclosing.map(|e| Rc::clone(&e)),
         }
     }
 
-}
\ No newline at end of file
+    /// A block, call, loop iteration or match arm scope, which keeps its
+    /// variables in slots.
+    pub fn local(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Environment {
+            local: true,
+            ..Environment::new(Some(enclosing))
+        }
+    }
+
+}
+
+/// The error for reading or assigning a name that isn't declared anywhere in reach.
+pub fn undefined_variable(name: &Token) -> RuntimeException {
+    RuntimeException::RuntimeError(RuntimeError::new(name, &format!("Undefined variable '{}'.", name.lexeme)))
+}
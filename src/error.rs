@@ -0,0 +1,34 @@
+use thiserror::Error;
+
+use crate::evaluator::RuntimeError;
+
+/// Source text the scanner couldn't turn into a token.
+#[derive(Debug, Clone, Error)]
+#[error("[line {line}] Error: {message}")]
+pub struct LexError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl LexError {
+    pub fn new(line: usize, message: String) -> Self {
+        LexError { line, message }
+    }
+}
+
+/// A program the parser or resolver rejected. The message
+/// already says where, as `[line N] Error at 'x': ...`.
+#[derive(Debug, Clone, Error)]
+#[error("{0}")]
+pub struct ParseError(pub String);
+
+/// Anything that stops a Lox program, by the stage that found it.
+#[derive(Debug, Error)]
+pub enum LoxError {
+    #[error(transparent)]
+    Lex(#[from] LexError),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
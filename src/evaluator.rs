@@ -1,21 +1,44 @@
 use std::cell::RefCell;
 use std::fmt;
+use std::fs;
+use std::path::Path;
 use std::rc::Rc;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{any::Any, process};
+use std::any::Any;
 
-use crate::environment::Environment;
+use crate::checker;
+use crate::environment::{undefined_variable, Environment};
+use crate::error::LoxError;
+use crate::flow;
 
-use crate::expr::LoxCallables;
+use crate::expr::{ExprKind, KeywordArg, LoxCallables, NodeId, Pattern, Property};
 use crate::lox_callable::LoxCallable;
 use crate::lox_function::{LoxAnonymous, LoxFunction};
-use crate::stmt::FunctionStmt;
+use crate::lox_object::LoxObject;
+use crate::module::{display_path, ModuleLoader, Namespace};
+use crate::optimizer;
+use crate::parser::Parser;
+use crate::resolver::{Local, Locals, Resolver};
+use crate::scanner::Scanner;
+use crate::stmt::{FunctionStmt, ImportStmt, MatchPattern, MatchStmt};
 use crate::token::{Token, TokenType};
 use crate::{expr::{Expr, Literal}, stmt::Stmt, visitor::{ExprAccept, ExprVisitor, StmtAccept, StmtVisitor}};
 
+/// How deeply calls may nest unless `set_max_depth` says otherwise. The
+/// interpreter thread in main.rs is given a stack that fits this many calls
+/// several times over, so running out of calls comes before running out of stack.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
 pub struct Evaluator {
     env: Rc<RefCell<Environment>>,
+    // The script's top level, and the natives that it and every module see.
     pub globals: Rc<RefCell<Environment>>,
+    pub builtins: Rc<RefCell<Environment>>,
+    modules: ModuleLoader,
+    locals: Locals,
+    // How many calls are running, and how many may before a call is refused.
+    depth: usize,
+    max_depth: usize,
 }
 
 
@@ -27,117 +50,123 @@ impl ExprVisitor for Evaluator {
             Literal::Number(n) => Ok(Box::from(Literal::Number(*n))),
             Literal::Str(str) => Ok(Box::new(Literal::Str(String::from(str)))),
             Literal::LoxCallable(lc) => Ok(Box::from(Literal::LoxCallable(lc.clone()))),
+            Literal::Namespace(ns) => Ok(Box::from(Literal::Namespace(ns.clone()))),
+            Literal::List(items) => Ok(Box::from(Literal::List(items.clone()))),
+            Literal::Object(object) => Ok(Box::from(Literal::Object(object.clone()))),
         }
     }
 
     fn visit_grouping(&mut self, box_expr: &Box<Expr>) -> Result<Box<Literal>, RuntimeException> {
-        self.evaluate(&box_expr)
+        self.evaluate(box_expr)
     }
     
     fn visit_unary(&mut self, op: &Box<Token>, un: &Box<Expr>) -> Result<Box<Literal>, RuntimeException> {
-        let _r = self.evaluate(&un)?;
-        match (*op).token_type {
+        let _r = self.evaluate(un)?;
+        match op.token_type {
             TokenType::Minus => {
                 if let Literal::Number(num) = *_r {
-                    return Ok(Box::new(Literal::Number(-num)));
+                    Ok(Box::new(Literal::Number(-num)))
                 }
                 else {
-                    return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number.")));
+                    Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number.")))
                 }
             },
             TokenType::Bang => {
-                return Ok(Box::new(Literal::Boolean(!self.is_truthy(&_r))));
+                Ok(Box::new(Literal::Boolean(!self.is_truthy(&_r))))
             }
-            _ => return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number."))),
+            _ => Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number."))),
         }
     }
 
     fn visit_binary(&mut self, op: &Box<Token>, left: &Box<Expr>, right: &Box<Expr>) -> Result<Box<Literal>, RuntimeException> {
-        let _op_left = self.evaluate(&left)?;
-        let _op_right = self.evaluate(&right)?;
+        let _op_left = self.evaluate(left)?;
+        let _op_right = self.evaluate(right)?;
 
-        match (*op).token_type {
+        match op.token_type {
             TokenType::Plus => {
                 if let (Literal::Number(l), Literal::Number(r)) = (&*_op_left, &*_op_right) {
                     return Ok(Box::new(Literal::Number(*l + *r)))
                 }
                 if let (Literal::Str(l), Literal::Str(r)) = (&*_op_left, &*_op_right) {
                     let mut res = String::from(l);
-                    res.push_str(&r);
+                    res.push_str(r);
                     return Ok(Box::new(Literal::Str(res)));
                 }
-                return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be two numbers or two strings.")));
+                Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be two numbers or two strings.")))
             }
             TokenType::Minus => {
                 if let (Literal::Number(l), Literal::Number(r)) = (&*_op_left, &*_op_right) {
                     return Ok(Box::new(Literal::Number(*l - *r)));
                 }
-                return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number.")));
+                Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number.")))
             }
             TokenType::Star => {
                 if let (Literal::Number(l), Literal::Number(r)) = (&*_op_left, &*_op_right) {
                     return Ok(Box::new(Literal::Number(*l * *r)));
                 }
-                return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number.")));
+                Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number.")))
             }
             TokenType::Slash => {
                 if let (Literal::Number(l), Literal::Number(r)) = (&*_op_left, &*_op_right) {
                     if *r == 0.0 {
-                        process::exit(65);
+                        return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Division by zero.")));
                     }
                     return Ok(Box::new(Literal::Number(*l / *r)))
                 }
-                return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number.")));
+                Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operand must be a number.")))
             }
             TokenType::Greater => {
                 if let (Literal::Number(l), Literal::Number(r)) = (&*_op_left, &*_op_right) {
                     return Ok(Box::new(Literal::Boolean(*l > *r)));
                 }
-                return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be numbers.")));
+                Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be numbers.")))
             }
             TokenType::GreaterEqual => {
                 if let (Literal::Number(l), Literal::Number(r)) = (&*_op_left, &*_op_right) {
                     return Ok(Box::new(Literal::Boolean(*l >= *r)));
                 }
-                return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be numbers.")));
+                Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be numbers.")))
             }
             TokenType::Less => {
                 if let (Literal::Number(l), Literal::Number(r)) = (&*_op_left, &*_op_right) {
                     return Ok(Box::new(Literal::Boolean(*l < *r)));
                 }
-                return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be numbers.")));
+                Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be numbers.")))
             }
             TokenType::LessEqual => {
                 if let (Literal::Number(l), Literal::Number(r)) = (&*_op_left, &*_op_right) {
                     return Ok(Box::new(Literal::Boolean(*l <= *r)));
                 }
-                return Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be numbers.")));
+                Err(RuntimeException::RuntimeError(RuntimeError::new(op, "Operands must be numbers.")))
             }
             TokenType::EqualEqual => {
-                return Ok(Box::new(Literal::Boolean(self.is_equal(&_op_left, &_op_right))));
+                Ok(Box::new(Literal::Boolean(self.is_equal(&_op_left, &_op_right))))
             }
             TokenType::BangEqual => {
-                return Ok(Box::new(Literal::Boolean(!self.is_equal(&_op_left, &_op_right))));
+                Ok(Box::new(Literal::Boolean(!self.is_equal(&_op_left, &_op_right))))
             }
-            _ => return Err(RuntimeException::RuntimeError(RuntimeError::new(op, ""))),
+            _ => Err(RuntimeException::RuntimeError(RuntimeError::new(op, &format!("Unknown binary operator '{}'.", op.lexeme)))),
         }
 
     }
     
-    fn visit_variable(&mut self, name: &Box<Token>) -> Result<Box<Literal>, RuntimeException> {
-        let a= self.env.borrow().get(&name)?;
+    fn visit_variable(&mut self, id: NodeId, name: &Box<Token>) -> Result<Box<Literal>, RuntimeException> {
+        let a = match self.locals.variable(id) {
+            Some(local) => Environment::get_at(&self.env, local, name)?,
+            None => self.env.borrow().get(name)?,
+        };
 
         if let Some(_val) = a {
             return Ok(Box::from(*_val.clone()));
         }
 
-        return Err(RuntimeException::RuntimeError(RuntimeError::new(&name, "")));
+        Err(undefined_variable(name))
     }
     
-    fn visit_assign(&mut self, name: &Box<Token>, a: &Box<Expr>) -> Result<Box<Literal>, RuntimeException> {
-        let val = self.evaluate(&**a)?;
-        self.env.borrow_mut().assign(*&name, Some(&val))?;
-        return Ok(val);
+    fn visit_assign(&mut self, id: NodeId, name: &Box<Token>, a: &Box<Expr>) -> Result<Box<Literal>, RuntimeException> {
+        let val = self.evaluate(a)?;
+        self.assign_variable(self.locals.variable(id), name, &val)?;
+        Ok(val)
     }
     
     fn visit_logical(&mut self, left: &Box<Expr>, op: &Box<Token>, right: &Box<Expr>) -> Result<Box<Literal>, RuntimeException> {
@@ -154,34 +183,75 @@ impl ExprVisitor for Evaluator {
             }
         }
 
-        self.evaluate(&right)
+        self.evaluate(right)
     }
     
-    fn visit_call(&mut self, callee: &Box<Expr>, paren: &Box<Token>, arguments: &Box<Vec<Expr>>) -> Result<Box<Literal>, RuntimeException> {
-        let callee = *self.evaluate(&callee)?;
+    fn visit_call(&mut self, callee: &Box<Expr>, paren: &Box<Token>, arguments: &Box<Vec<Expr>>, keywords: &Box<Vec<KeywordArg>>) -> Result<Box<Literal>, RuntimeException> {
+        let (callee, args, kwargs) = self.evaluate_call(callee, arguments, keywords)?;
+        self.call_value(callee, paren, args, kwargs)
+    }
 
-        let mut args= Vec::new();
-        for arg in arguments.iter() {
-            args.push(*self.evaluate(arg)?);
+    fn visit_get(&mut self, object: &Box<Expr>, name: &Box<Token>) -> Result<Box<Literal>, RuntimeException> {
+        let object = self.evaluate(object)?;
+
+        match &*object {
+            Literal::Namespace(ns) => ns.env.borrow().get_own(&name.lexeme).ok_or_else(|| {
+                RuntimeException::RuntimeError(RuntimeError::new(name, &format!("Undefined member '{}' in module '{}'.", name.lexeme, ns.name)))
+            }),
+            Literal::Object(obj) => obj.borrow().get(&name.lexeme).map(Box::from).ok_or_else(|| {
+                RuntimeException::RuntimeError(RuntimeError::new(name, &format!("Undefined property '{}'.", name.lexeme)))
+            }),
+            _ => Err(RuntimeException::RuntimeError(RuntimeError::new(name, "Only objects and modules have properties."))),
         }
+    }
+
+    fn visit_list(&mut self, elements: &Box<Vec<Expr>>) -> Result<Box<Literal>, RuntimeException> {
+        let mut items = Vec::new();
+        for element in elements.iter() {
+            items.push(*self.evaluate(element)?);
+        }
+        Ok(Box::from(Literal::List(Rc::new(RefCell::new(items)))))
+    }
+
+    fn visit_index(&mut self, object: &Box<Expr>, bracket: &Box<Token>, index: &Box<Expr>) -> Result<Box<Literal>, RuntimeException> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
 
-        let function = match callee {
-            Literal::LoxCallable(lit) => Ok(lit),
-            _ => Err(RuntimeException::RuntimeError(RuntimeError::new(&paren, ""))),
+        let position = match *index {
+            Literal::Number(n) if n >= 0.0 && n.fract() == 0.0 => n as usize,
+            Literal::Number(_) => return Err(RuntimeException::RuntimeError(RuntimeError::new(bracket, "Index must be a non-negative integer."))),
+            _ => return Err(RuntimeException::RuntimeError(RuntimeError::new(bracket, "Index must be a number."))),
         };
 
-        if args.len() != function.clone()?.arrity() {
-            return Err(RuntimeException::RuntimeError(RuntimeError::new(&paren, &format!("Expected {} arguments but got {}.", function?.clone().arrity(), arguments.len()))));
+        let item = match &*object {
+            Literal::List(items) => items.borrow().get(position).cloned(),
+            Literal::Str(s) => s.chars().nth(position).map(|c| Literal::Str(c.to_string())),
+            _ => return Err(RuntimeException::RuntimeError(RuntimeError::new(bracket, "Only lists and strings can be indexed."))),
+        };
+
+        item.map(Box::from)
+            .ok_or_else(|| RuntimeException::RuntimeError(RuntimeError::new(bracket, "Index out of range.")))
+    }
+
+    fn visit_object(&mut self, properties: &Box<Vec<Property>>) -> Result<Box<Literal>, RuntimeException> {
+        let mut object = LoxObject::new();
+        for property in properties.iter() {
+            let value = self.evaluate(&property.value)?;
+            object.set(property.name.lexeme.clone(), *value);
         }
+        Ok(Box::from(Literal::Object(Rc::new(RefCell::new(object)))))
+    }
 
-        let res = function?.callq(self, args);
+    fn visit_destructure_assign(&mut self, id: NodeId, pattern: &Box<Pattern>, value: &Box<Expr>) -> Result<Box<Literal>, RuntimeException> {
+        let value = self.evaluate(value)?;
 
-        return match res {
-            Err(RuntimeException::Return(value)) => Ok(Box::from(value.value.unwrap())),
-            Ok(Some(val)) => Ok(val),
-            Ok(None) => Ok(Box::from(Literal::Nil)),
-            Err(e) => Err(e),
-        };
+        let mut bindings = Vec::new();
+        destructure(pattern, (*value).clone(), &mut bindings)?;
+        for (i, (name, bound)) in bindings.into_iter().enumerate() {
+            let local = self.locals.pattern(id, i);
+            self.assign_variable(local, &name, &Box::from(bound))?;
+        }
+        Ok(value)
     }
     
 }
@@ -192,56 +262,51 @@ impl Evaluator {
         expr.accept(self)
     }
 
-    fn is_equal(&self, l: &Box<Literal>, r: &Box<Literal>) -> bool {
+    fn is_equal(&self, l: &Literal, r: &Literal) -> bool {
         if l.type_id() != r.type_id() {
             return false;
         }
-        match (&**l, &**r) {
-            (Literal::Boolean(l_val), Literal::Boolean(r_val)) => return *l_val == *r_val,
-            (Literal::Number(l_val), Literal::Number(r_val)) => return *l_val == *r_val,
-            (Literal::Str(l_val), Literal::Str(r_val)) => return *l_val == *r_val,
-            _ => return false,
+        match (l, r) {
+            (Literal::Boolean(l_val), Literal::Boolean(r_val)) => *l_val == *r_val,
+            (Literal::Number(l_val), Literal::Number(r_val)) => *l_val == *r_val,
+            (Literal::Str(l_val), Literal::Str(r_val)) => *l_val == *r_val,
+            (Literal::List(l_val), Literal::List(r_val)) => Rc::ptr_eq(l_val, r_val),
+            (Literal::Object(l_val), Literal::Object(r_val)) => Rc::ptr_eq(l_val, r_val),
+            _ => false,
         }
     }
 
-    pub fn is_truthy(&self, val: &Box<Literal>) -> bool {
-        if **val == Literal::Nil {
+    pub fn is_truthy(&self, val: &Literal) -> bool {
+        if *val == Literal::Nil {
             return false;
         }
 
-        if let Literal::Boolean(bool_val) = &**val {
+        if let Literal::Boolean(bool_val) = val {
             return *bool_val;
         }
 
-        return true;
+        true
     }
 
 
-    pub fn writer(&self, value: &Box<Literal>) {
-        match &**value {
-            Literal::Nil => println!("nil"),
-            Literal::Boolean(val) => println!("{}", val),
-            Literal::Number(val) => println!("{}", val),
-            Literal::Str(val) => println!("{}", val),
-            Literal::LoxCallable(lc) => println!("{}", lc),
-        }
+    pub fn writer(&self, value: &Literal) {
+        println!("{}", value);
     }
 }
 
 impl StmtVisitor for Evaluator  {
     fn visit_expression_stmt(&mut self, stmt: &Box<Expr>) -> Result<(), RuntimeException> {
-        self.evaluate(&stmt)?;
+        self.evaluate(stmt)?;
         Ok(())
     }
     
     fn visit_print_stmt(&mut self, stmt: &Box<Expr>) -> Result<(), RuntimeException> {
-        match self.evaluate(&stmt) {
-            Ok(d) => Ok(self.writer(&d)),
-            Err(e) => Err(e),
-        }
+        let d = self.evaluate(stmt)?;
+        self.writer(&d);
+        Ok(())
     }
     
-    fn visit_declaration(&mut self, id: &String, initializer: &Option<Box<Expr>>) -> Result<(), RuntimeException> {
+    fn visit_declaration(&mut self, name: &Token, initializer: &Option<Box<Expr>>, constant: bool) -> Result<(), RuntimeException> {
         let value = if let Some(expr) = initializer {
             Some(self.evaluate(expr)?)
         }
@@ -249,23 +314,33 @@ impl StmtVisitor for Evaluator  {
             Some(Box::from(Literal::Nil) as Box<Literal>)
         };
 
-        self.env.borrow_mut().define(id.to_string(), value);
-        Ok(())
+        self.env.borrow_mut().declare(name, value, constant)
     }
     
+    fn visit_destructure(&mut self, pattern: &Box<Pattern>, initializer: &Box<Expr>, constant: bool) -> Result<(), RuntimeException> {
+        let value = self.evaluate(initializer)?;
+
+        let mut bindings = Vec::new();
+        destructure(pattern, *value, &mut bindings)?;
+        for (name, bound) in bindings {
+            self.env.borrow_mut().declare(&name, Some(Box::from(bound)), constant)?;
+        }
+        Ok(())
+    }
+
     fn visit_block(&mut self, v: &Box<Vec<Stmt>>) -> Result<(), RuntimeException> {
-        let new_env = Rc::new(RefCell::new(Environment::new(Some(self.env.clone()))));
-        self.execute_block(&v, new_env)
+        let new_env = Rc::new(RefCell::new(Environment::local(self.env.clone())));
+        self.execute_block(v, new_env)
     }
     
     fn visit_if(&mut self, expr: &Box<Expr>, fi: &Box<Stmt>, esl: &Option<Box<Stmt>>) -> Result<(), RuntimeException> {
         let c = self.evaluate(expr)?;
 
         if self.is_truthy(&c) {
-            self.execute(&**fi)?;
+            self.execute(fi)?;
         }
         else if let Some(else_val) = esl {
-            self.execute(&**else_val)?;
+            self.execute(else_val)?;
         }
 
         Ok(())
@@ -275,47 +350,259 @@ impl StmtVisitor for Evaluator  {
         let mut cond = self.evaluate(expr)?;
 
         while self.is_truthy(&cond) {
-            self.execute(&st)?;
+            self.execute(st)?;
             cond = self.evaluate(expr)?;
         }
 
         Ok(())
     }
     
+    fn visit_do_while(&mut self, st: &Box<Stmt>, expr: &Box<Expr>) -> Result<(), RuntimeException> {
+        loop {
+            self.execute(st)?;
+            let cond = self.evaluate(expr)?;
+            if !self.is_truthy(&cond) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn visit_for_in(&mut self, name: &Box<Token>, iterable: &Box<Expr>, st: &Box<Stmt>) -> Result<(), RuntimeException> {
+        let iterable = *self.evaluate(iterable)?;
+
+        match &iterable {
+            Literal::Str(s) => {
+                for c in s.chars() {
+                    self.run_iteration(name, Literal::Str(c.to_string()), st)?;
+                }
+            }
+            Literal::List(items) => {
+                // Index into the live list so the body sees its own appends.
+                for i in 0.. {
+                    let item = items.borrow().get(i).cloned();
+                    let Some(item) = item else {
+                        break;
+                    };
+                    self.run_iteration(name, item, st)?;
+                }
+            }
+            Literal::Object(_) | Literal::Namespace(_) => {
+                let (Some(has_next), Some(next)) = (property(&iterable, "hasNext"), property(&iterable, "next")) else {
+                    return Err(RuntimeException::RuntimeError(RuntimeError::new(name, "Can only iterate over objects with 'hasNext' and 'next' methods.")));
+                };
+                loop {
+                    let more = self.call_value(has_next.clone(), name, Vec::new(), Vec::new())?;
+                    if !self.is_truthy(&more) {
+                        break;
+                    }
+                    let item = self.call_value(next.clone(), name, Vec::new(), Vec::new())?;
+                    self.run_iteration(name, *item, st)?;
+                }
+            }
+            _ => return Err(RuntimeException::RuntimeError(RuntimeError::new(name, &format!("Can't iterate over a {}.", type_name(&iterable))))),
+        }
+        Ok(())
+    }
+
     fn visit_function(&mut self, fun_stmt: &Box<FunctionStmt>) -> Result<(), RuntimeException> {
         let function = LoxFunction::new(*fun_stmt.clone(), self.env.clone());
-        self.env.borrow_mut().define(fun_stmt.name.lexeme.clone(),
-                                    Some(Box::from(Literal::LoxCallable(LoxCallables::LoxFunction(Box::from(function))))));
-        Ok(())
+        self.env.borrow_mut().declare(&fun_stmt.name,
+                                    Some(Box::from(Literal::LoxCallable(LoxCallables::LoxFunction(Box::from(function))))), false)
     }
     
     fn visit_return(&mut self, _tok: &Box<Token>, exp: &Option<Box<Expr>>) -> Result<(), RuntimeException> {
+        // A call in tail position is made by `call_value` once this frame has
+        // unwound, so tail-recursive functions run in constant stack.
+        if let Some(ExprKind::Call(callee, paren, arguments, keywords)) = exp.as_ref().map(|expr| &expr.kind) {
+            let (callee, args, kwargs) = self.evaluate_call(callee, arguments, keywords)?;
+            return Err(RuntimeException::TailCall(Box::new(TailCall::new(callee, (**paren).clone(), args, kwargs))));
+        }
+
         let value = match exp {
             Some(expr) => self.evaluate(expr)?,
             None => Box::from(Literal::Nil),
         };
         
-        return Err(RuntimeException::Return(Return::new(Some(*value))));
+        Err(RuntimeException::Return(Return::new(Some(*value))))
+    }
+
+    fn visit_match(&mut self, match_stmt: &Box<MatchStmt>) -> Result<(), RuntimeException> {
+        let value = self.evaluate(&match_stmt.subject)?;
+
+        for arm in &match_stmt.arms {
+            if !arm.pattern.matches(&value) {
+                continue;
+            }
+
+            let env = Rc::new(RefCell::new(Environment::local(self.env.clone())));
+            if let MatchPattern::Binding(name) = &arm.pattern {
+                env.borrow_mut().define(name.lexeme.clone(), Some(value.clone()));
+            }
+
+            if let Some(guard) = &arm.guard {
+                let passed = self.evaluate_in(guard, env.clone())?;
+                if !self.is_truthy(&passed) {
+                    continue;
+                }
+            }
+
+            return self.execute_in(&arm.body, env);
+        }
+
+        Err(RuntimeException::RuntimeError(RuntimeError::new(&match_stmt.keyword, &format!("No match arm matched value {}.", value))))
+    }
+
+    fn visit_import(&mut self, import: &Box<ImportStmt>) -> Result<(), RuntimeException> {
+        let namespace = self.load_module(&import.keyword, &import.path)?;
+
+        if let Some(alias) = &import.alias {
+            return self.env.borrow_mut().declare(alias, Some(Box::from(Literal::Namespace(namespace))), false);
+        }
+
+        for name in &import.names {
+            let value = namespace.env.borrow().get_own(&name.lexeme).ok_or_else(|| {
+                RuntimeException::RuntimeError(RuntimeError::new(name, &format!("Module '{}' has no member '{}'.", import.path, name.lexeme)))
+            })?;
+            self.env.borrow_mut().declare(name, Some(value), false)?;
+        }
+        Ok(())
     }
     
     
 }
 
 impl Evaluator {
-    pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), RuntimeException> { 
-        for stmt in stmts {
-            match self.execute(&stmt) {
-                Ok(_) => (),
-                Err(e) => {
-                    eprintln!("{}", e);
-                    process::exit(70);
+    pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), LoxError> {
+        match self.execute_all(&stmts) {
+            Err(RuntimeException::RuntimeError(e)) => Err(LoxError::Runtime(e)),
+            // The resolver rejects `return` outside a function, so nothing else gets here.
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs the resolver over statements about to be executed and keeps the
+    /// locals it found.
+    pub fn resolve(&mut self, stmts: &[Stmt]) -> Result<(), String> {
+        let locals = Resolver::new().resolve(stmts)?;
+        self.locals.extend(locals);
+        Ok(())
+    }
+
+    // Writes a resolved local into its slot and anything else by name.
+    fn assign_variable(&mut self, local: Option<Local>, name: &Token, value: &Literal) -> Result<(), RuntimeException> {
+        match local {
+            Some(local) => Environment::assign_at(&self.env, local, name, Some(value)),
+            None => self.env.borrow_mut().assign(name, Some(value)),
+        }
+    }
+
+    /// Runs statements in the current environment, stopping at the first error.
+    pub fn execute_all(&mut self, stmts: &[Stmt]) -> Result<(), RuntimeException> {
+        stmts.iter().try_for_each(|stmt| self.execute(stmt))
+    }
+
+    pub fn call_value(&mut self, callee: Literal, paren: &Token, args: Vec<Literal>, kwargs: Vec<(Token, Literal)>) -> Result<Box<Literal>, RuntimeException> {
+        let mut call = TailCall::new(callee, paren.clone(), args, kwargs);
+        // The function that handed back the tail call this pass makes, if any.
+        let mut tail_caller: Option<String> = None;
+
+        // Each pass makes one call; a tail call the callee hands back is made
+        // by the next pass instead of nesting inside it.
+        loop {
+            let checked = match call.callee {
+                Literal::LoxCallable(lit) => Ok(lit),
+                _ => Err(RuntimeError::new(&call.paren, "Can only call functions.")),
+            }.and_then(|function| {
+                // With keyword arguments in play the callee reports exactly which
+                // parameter is missing or given twice, so only count positionals here.
+                let (min, max) = (function.arrity(), function.max_arrity());
+                if (call.kwargs.is_empty() && call.args.len() < min) || max.is_some_and(|max| call.args.len() > max) {
+                    return Err(RuntimeError::new(&call.paren, &arity_message(min, max, call.args.len())));
+                }
+                if self.depth >= self.max_depth {
+                    return Err(RuntimeError::new(&call.paren, "Stack overflow."));
+                }
+                Ok(function)
+            });
+            let function = match checked {
+                Ok(function) => function,
+                Err(mut e) => {
+                    // A tail call that can't be made fails in the function that made it.
+                    if let Some(tail_caller) = &tail_caller {
+                        e.unwind(tail_caller, paren);
+                    }
+                    return Err(RuntimeException::RuntimeError(e));
                 }
+            };
+
+            self.depth += 1;
+            let mut res = function.callq(self, call.args, call.kwargs);
+            self.depth -= 1;
+            // The caller is waiting on the call it made, even when a tail call
+            // has since replaced the function it called.
+            if let (Err(RuntimeException::RuntimeError(e)), LoxCallables::LoxFunction(lox_function)) = (&mut res, &function) {
+                e.unwind(&lox_function.declaration.name.lexeme, paren);
             }
+
+            return match res {
+                Err(RuntimeException::TailCall(next)) => {
+                    if let LoxCallables::LoxFunction(lox_function) = &function {
+                        tail_caller = Some(lox_function.declaration.name.lexeme.clone());
+                    }
+                    call = *next;
+                    continue;
+                }
+                Err(RuntimeException::Return(value)) => Ok(Box::from(value.value.unwrap())),
+                Ok(Some(val)) => Ok(val),
+                Ok(None) => Ok(Box::from(Literal::Nil)),
+                Err(e) => Err(e),
+            };
         }
-        Ok(())
     }
 
-    pub fn execute_block(&mut self, statements: &Vec<Stmt>, new_env: Rc<RefCell<Environment>>) -> Result<(), RuntimeException> {
+    fn evaluate_call(&mut self, callee: &Expr, arguments: &[Expr], keywords: &[KeywordArg]) -> Result<CallParts, RuntimeException> {
+        let callee = *self.evaluate(callee)?;
+
+        let mut args= Vec::new();
+        for arg in arguments.iter() {
+            args.push(*self.evaluate(arg)?);
+        }
+
+        let mut kwargs = Vec::new();
+        for keyword in keywords.iter() {
+            kwargs.push((keyword.name.clone(), *self.evaluate(&keyword.value)?));
+        }
+
+        Ok((callee, args, kwargs))
+    }
+
+    fn run_iteration(&mut self, name: &Token, value: Literal, body: &Stmt) -> Result<(), RuntimeException> {
+        let env = Rc::new(RefCell::new(Environment::local(self.env.clone())));
+        env.borrow_mut().define(name.lexeme.clone(), Some(Box::from(value)));
+        self.execute_in(body, env)
+    }
+
+    pub fn evaluate_in(&mut self, expr: &Expr, env: Rc<RefCell<Environment>>) -> Result<Box<Literal>, RuntimeException> {
+        let previous = self.env.clone();
+        self.env = env;
+
+        let result = self.evaluate(expr);
+
+        self.env = previous;
+        result
+    }
+
+    pub fn execute_in(&mut self, stmt: &Stmt, env: Rc<RefCell<Environment>>) -> Result<(), RuntimeException> {
+        let previous = self.env.clone();
+        self.env = env;
+
+        let result = self.execute(stmt);
+
+        self.env = previous;
+        result
+    }
+
+    pub fn execute_block(&mut self, statements: &[Stmt], new_env: Rc<RefCell<Environment>>) -> Result<(), RuntimeException> {
         let previous = self.env.clone();
         self.env = new_env;
         
@@ -330,8 +617,64 @@ impl Evaluator {
         stmt.accept(self)
     }
 
-    pub fn new(globals: Rc<RefCell<Environment>>) -> Self {
-        globals.borrow_mut().define("clock".to_owned(),
+    pub fn set_script(&mut self, script: &Path) {
+        self.modules.set_script(script);
+    }
+
+    /// Sets how deeply calls may nest before one fails with "Stack overflow.".
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Runs the module at `path` in its own environment the first time it is
+    /// imported and hands out the cached namespace on every later import.
+    fn load_module(&mut self, keyword: &Token, path: &str) -> Result<Rc<Namespace>, RuntimeException> {
+        let error = |message: String| RuntimeException::RuntimeError(RuntimeError::new(keyword, &message));
+
+        let resolved = self.modules.resolve(path)
+            .ok_or_else(|| error(format!("Cannot find module '{}'.", path)))?;
+
+        if let Some(namespace) = self.modules.cached(&resolved) {
+            return Ok(namespace);
+        }
+        if let Some(chain) = self.modules.cycle(&resolved) {
+            return Err(error(format!("Import cycle detected: {}.", chain)));
+        }
+
+        let source = fs::read_to_string(&resolved)
+            .map_err(|e| error(format!("Cannot read module '{}': {}.", display_path(&resolved), e)))?;
+        let mut tokenizer = Scanner::new(&source);
+        tokenizer.scan_tokens();
+        if let Some(e) = tokenizer.errors.first() {
+            return Err(error(format!("Cannot tokenize module '{}': {}", display_path(&resolved), e)));
+        }
+        let mut parser = Parser::new(&tokenizer.tokens);
+        let stmts = parser._parse()
+            .map_err(|e| error(format!("Error in module '{}': {}", display_path(&resolved), e)))?;
+        // A module sees the natives but nothing of the script importing it.
+        let env = Rc::new(RefCell::new(Environment::new(Some(self.builtins.clone()))));
+        self.resolve(&stmts)
+            .map_err(|e| error(format!("Error in module '{}': {}", display_path(&resolved), e)))?;
+        let analyses = [checker::check(&stmts, &env.borrow()), flow::analyze(&stmts, &env.borrow())];
+        for warning in parser.warnings.iter().chain(analyses.iter().flatten()) {
+            eprintln!("{}: {}", display_path(&resolved), warning);
+        }
+        let stmts = optimizer::optimize(stmts);
+
+        self.modules.enter(resolved.clone());
+        let result = self.execute_block(&stmts, env.clone());
+        self.modules.leave();
+        result?;
+
+        let name = resolved.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let namespace = Rc::new(Namespace::new(name, env));
+        self.modules.insert(resolved, namespace.clone());
+        Ok(namespace)
+    }
+
+    pub fn new() -> Self {
+        let builtins = Rc::new(RefCell::new(Environment::new(None)));
+        builtins.borrow_mut().define("clock".to_owned(),
             Some(Box::from(Literal::LoxCallable(LoxCallables::LoxAnonymous(
                 Box::new(LoxAnonymous::new(|_interpreter, _arguments| {
                     Ok(Some(Box::from(Literal::Number(
@@ -341,28 +684,141 @@ impl Evaluator {
                 )),
             ))),
         ));
+        let globals = Rc::new(RefCell::new(Environment::new(Some(builtins.clone()))));
         Evaluator {
             env: globals.clone(),
-            globals
+            globals,
+            builtins,
+            modules: ModuleLoader::new(),
+            locals: Locals::default(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
         }
     }
 }
 
-#[derive(Clone)]
+/// Matches `value` against `pattern`, collecting the name each piece binds to.
+fn destructure(pattern: &Pattern, value: Literal, bindings: &mut Vec<(Token, Literal)>) -> Result<(), RuntimeException> {
+    let error = |token: &Token, message: String| RuntimeException::RuntimeError(RuntimeError::new(token, &message));
+
+    match pattern {
+        Pattern::Name(name) => bindings.push(((**name).clone(), value)),
+        Pattern::List(bracket, elements, rest) => {
+            let items = match &value {
+                Literal::List(items) => items.borrow().clone(),
+                _ => return Err(error(bracket, format!("Expected a list to destructure but got {}.", type_name(&value)))),
+            };
+
+            if items.len() < elements.len() || (rest.is_none() && items.len() > elements.len()) {
+                let expected = if rest.is_some() { format!("at least {}", elements.len()) } else { elements.len().to_string() };
+                return Err(error(bracket, format!("Expected {} elements but got {}.", expected, items.len())));
+            }
+
+            let mut items = items.into_iter();
+            for (element, item) in elements.iter().zip(items.by_ref()) {
+                destructure(element, item, bindings)?;
+            }
+            if let Some(rest) = rest {
+                bindings.push(((**rest).clone(), Literal::List(Rc::new(RefCell::new(items.collect())))));
+            }
+        }
+        Pattern::Object(brace, fields) => {
+            for (key, field_pattern) in fields {
+                if !matches!(value, Literal::Object(_) | Literal::Namespace(_)) {
+                    return Err(error(brace, format!("Expected an object to destructure but got {}.", type_name(&value))));
+                }
+                let field = property(&value, &key.lexeme);
+                let field = field.ok_or_else(|| error(key, format!("Missing property '{}' in destructured value.", key.lexeme)))?;
+                destructure(field_pattern, field, bindings)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn property(value: &Literal, name: &str) -> Option<Literal> {
+    match value {
+        Literal::Object(object) => object.borrow().get(name),
+        Literal::Namespace(ns) => ns.env.borrow().get_own(name).map(|field| *field),
+        _ => None,
+    }
+}
+
+pub fn type_name(value: &Literal) -> &'static str {
+    match value {
+        Literal::Number(_) => "number",
+        Literal::Str(_) => "string",
+        Literal::Boolean(_) => "boolean",
+        Literal::LoxCallable(_) => "function",
+        Literal::Namespace(_) => "module",
+        Literal::List(_) => "list",
+        Literal::Object(_) => "object",
+        Literal::Nil => "nil",
+    }
+}
+
+// A callee and the arguments it is called with, all evaluated.
+type CallParts = (Literal, Vec<Literal>, Vec<(Token, Literal)>);
+
+pub fn arity_message(min: usize, max: Option<usize>, got: usize) -> String {
+    match max {
+        Some(max) if max == min => format!("Expected {} arguments but got {}.", min, got),
+        Some(max) => format!("Expected {} to {} arguments but got {}.", min, max, got),
+        None => format!("Expected at least {} arguments but got {}.", min, got),
+    }
+}
+
+// How many calls are shown at each end of a long stack trace.
+const TRACE_END: usize = 10;
+
+#[derive(Debug, Clone)]
 pub struct RuntimeError {
     pub token: Token,
     pub message: String,
+    // The Lox functions the error has left so far, innermost first, each
+    // with the line it was called from. Boxed so a `Result` carrying a
+    // runtime error stays small.
+    #[allow(clippy::box_collection)]
+    calls: Box<Vec<(String, usize)>>,
 }
 
 impl RuntimeError {
     pub fn new(token: &Token, message: &str) -> Self {
-        return RuntimeError {
+        RuntimeError {
             token: token.clone(),
             message: message.to_string(),
-        };
+            calls: Box::default(),
+        }
+    }
+
+    /// Records the error leaving `function`, which was called at `call`.
+    pub fn unwind(&mut self, function: &str, call: &Token) {
+        self.calls.push((function.to_string(), call.line));
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n[line {}]", self.message, self.token.line)?;
+        // Each function was on the line of the call the one inside it came from.
+        // A deep trace keeps its ends; the middle of one is mostly a recursion.
+        let mut line = self.token.line;
+        let skipped = TRACE_END..self.calls.len().saturating_sub(TRACE_END).max(TRACE_END);
+        for (index, (function, call_line)) in self.calls.iter().enumerate() {
+            if index == skipped.start && !skipped.is_empty() {
+                write!(f, "\n... {} more calls ...", skipped.len())?;
+            }
+            if !skipped.contains(&index) {
+                write!(f, "\nin {}() at line {}", function, line)?;
+            }
+            line = *call_line;
+        }
+        write!(f, "\nin <script>")
     }
 }
 
+impl std::error::Error for RuntimeError {}
+
 #[derive(Clone)]
 pub struct Return {
     pub value: Option<Literal>,
@@ -374,16 +830,32 @@ impl Return {
     }
 }
 
+/// A call in tail position, waiting for the caller's frame to be gone.
+#[derive(Clone)]
+pub struct TailCall {
+    pub callee: Literal,
+    pub paren: Token,
+    pub args: Vec<Literal>,
+    pub kwargs: Vec<(Token, Literal)>,
+}
+
+impl TailCall {
+    pub fn new(callee: Literal, paren: Token, args: Vec<Literal>, kwargs: Vec<(Token, Literal)>) -> Self {
+        TailCall { callee, paren, args, kwargs }
+    }
+}
+
 #[derive(Clone)]
 pub enum RuntimeException {
     RuntimeError(RuntimeError),
     Return(Return),
+    TailCall(Box<TailCall>),
 }
 
 impl fmt::Display for RuntimeException {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            RuntimeException::RuntimeError(e) => write!(f, "{}", e.message),
+            RuntimeException::RuntimeError(e) => write!(f, "{}", e),
             _ => write!(f, ""),
         }
     }
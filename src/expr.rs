@@ -1,6 +1,6 @@
-use std::{borrow::Cow, fmt};
+use std::{borrow::Cow, cell::RefCell, fmt, rc::Rc, sync::atomic::{AtomicUsize, Ordering}};
 
-use crate::{evaluator::RuntimeException, lox_function::{LoxAnonymous, LoxFunction}, token::Token, visitor::{ExprAccept, ExprVisitor}};
+use crate::{evaluator::RuntimeException, lox_function::{LoxAnonymous, LoxFunction}, lox_object::LoxObject, module::Namespace, token::{Span, Token}, visitor::{ExprAccept, ExprVisitor}};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
@@ -8,6 +8,9 @@ pub enum Literal {
     Str(String),
     Boolean(bool),
     LoxCallable(LoxCallables),
+    Namespace(Rc<Namespace>),
+    List(Rc<RefCell<Vec<Literal>>>),
+    Object(Rc<RefCell<LoxObject>>),
     Nil,
 }
 
@@ -17,67 +20,255 @@ pub enum LoxCallables {
     LoxAnonymous(Box<LoxAnonymous>),
 }
 
+/// Identifies one syntax tree node for the whole run, across every parsed
+/// file, so passes can keep what they learn about a node in side tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub usize);
+
+static NEXT_NODE_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl NodeId {
+    pub fn fresh() -> Self {
+        NodeId(NEXT_NODE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub id: NodeId,
+    pub span: Span,
+    pub kind: ExprKind,
+}
+
+impl Expr {
+    pub fn new(span: Span, kind: ExprKind) -> Self {
+        Expr { id: NodeId::fresh(), span, kind }
+    }
+}
+
+// Ids and spans say where a node came from, not what it is.
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+// Every variant keeps its children boxed, lists included, so the enum stays small.
+#[allow(clippy::box_collection)]
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expr {
+pub enum ExprKind {
     Lit(Literal),
     Unary(Box<Token>, Box<Expr>),
     Binary(Box<Expr>, Box<Token>, Box<Expr>),
-    Call(Box<Expr>, Box<Token>, Box<Vec<Expr>>),
+    Call(Box<Expr>, Box<Token>, Box<Vec<Expr>>, Box<Vec<KeywordArg>>),
     Grouping(Box<Expr>),
     Variable(Box<Token>),
     Assign(Box<Token>, Box<Expr>),
     Logical(Box<Expr>, Box<Token>, Box<Expr>),
+    Get(Box<Expr>, Box<Token>),
+    List(Box<Vec<Expr>>),
+    Index(Box<Expr>, Box<Token>, Box<Expr>),
+    Object(Box<Vec<Property>>),
+    Destructure(Box<Pattern>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeywordArg {
+    pub name: Token,
+    pub value: Expr,
+}
+
+impl KeywordArg {
+    pub fn new(name: Token, value: Expr) -> Self {
+        KeywordArg { name, value }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    pub name: Token,
+    pub value: Expr,
+}
+
+impl Property {
+    pub fn new(name: Token, value: Expr) -> Self {
+        Property { name, value }
+    }
+}
+
+/// The left-hand side of a destructuring declaration or assignment. The
+/// bracket and brace tokens are kept so shape mismatches can report a line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Name(Box<Token>),
+    List(Box<Token>, Vec<Pattern>, Option<Box<Token>>),
+    Object(Box<Token>, Vec<(Token, Pattern)>),
+}
+
+impl Pattern {
+    pub fn names(&self) -> Vec<&Token> {
+        match self {
+            Pattern::Name(name) => vec![name],
+            Pattern::List(_, elements, rest) => elements.iter()
+                .flat_map(Pattern::names)
+                .chain(rest.as_deref())
+                .collect(),
+            Pattern::Object(_, fields) => fields.iter()
+                .flat_map(|(_, pattern)| pattern.names())
+                .collect(),
+        }
+    }
 }
 
 impl ExprAccept for Expr {
     fn accept(&self, visitor: &mut dyn ExprVisitor) -> Result<Box<Literal>, RuntimeException> {
-        match self {
-            Expr::Lit(l) => visitor.visit_literal(l),
-            Expr::Grouping(gr) => visitor.visit_grouping(gr),
-            Expr::Unary(op, b) => visitor.visit_unary(op, b),
-            Expr::Binary(left, op, right) => visitor.visit_binary(op, left, right),
-            Expr::Variable(name) => visitor.visit_variable(name),
-            Expr::Assign(name, v) => visitor.visit_assign(name, v),
-            Expr::Logical(left, op, right) => visitor.visit_logical(left, op, right),
-            Expr::Call(callee, paren, arguments) => visitor.visit_call(callee, paren, arguments),
+        match &self.kind {
+            ExprKind::Lit(l) => visitor.visit_literal(l),
+            ExprKind::Grouping(gr) => visitor.visit_grouping(gr),
+            ExprKind::Unary(op, b) => visitor.visit_unary(op, b),
+            ExprKind::Binary(left, op, right) => visitor.visit_binary(op, left, right),
+            ExprKind::Variable(name) => visitor.visit_variable(self.id, name),
+            ExprKind::Assign(name, v) => visitor.visit_assign(self.id, name, v),
+            ExprKind::Logical(left, op, right) => visitor.visit_logical(left, op, right),
+            ExprKind::Call(callee, paren, arguments, keywords) => visitor.visit_call(callee, paren, arguments, keywords),
+            ExprKind::Get(object, name) => visitor.visit_get(object, name),
+            ExprKind::List(elements) => visitor.visit_list(elements),
+            ExprKind::Index(object, bracket, index) => visitor.visit_index(object, bracket, index),
+            ExprKind::Object(properties) => visitor.visit_object(properties),
+            ExprKind::Destructure(pattern, value) => visitor.visit_destructure_assign(self.id, pattern, value),
         }
     }
 }
 
 impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl fmt::Display for ExprKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Expr::Lit(Literal::Boolean(b)) => write!(f, "{}", b),
-            Expr::Lit(Literal::Nil) => write!(f, "nil"),
-            Expr::Lit(Literal::Str(s)) => write!(f, "{}", unescape(s)),
-            Expr::Lit(Literal::Number(n)) => write!(f, "{n:?}"),
-            Expr::Lit(Literal::LoxCallable(lc)) => write!(f, "{lc}"), 
-            Expr::Binary(left, operator, right) => write!(f, "({} {} {})", operator.lexeme, left, right),
-            Expr::Unary(operator, right) => write!(f, "({} {})", operator.lexeme, right),
-            Expr::Grouping(expr) => write!(f, "(group {})", expr),
-            Expr::Variable(s) => write!(f, "{}", s),
-            Expr::Assign(t, _) => write!(f, "{}", t),
-            Expr::Logical(_, _, _) => write!(f, ""),
-            Expr::Call(_, _, _) => write!(f, "ads"), 
+            ExprKind::Lit(Literal::Boolean(b)) => write!(f, "{}", b),
+            ExprKind::Lit(Literal::Nil) => write!(f, "nil"),
+            ExprKind::Lit(Literal::Str(s)) => write!(f, "{}", unescape(s)),
+            ExprKind::Lit(Literal::Number(n)) => write!(f, "{n:?}"),
+            ExprKind::Lit(Literal::LoxCallable(lc)) => write!(f, "{lc}"), 
+            ExprKind::Lit(Literal::Namespace(ns)) => write!(f, "{ns}"),
+            ExprKind::Lit(lit @ (Literal::List(_) | Literal::Object(_))) => write!(f, "{lit}"),
+            ExprKind::Binary(left, operator, right) => write!(f, "({} {} {})", operator.lexeme, left, right),
+            ExprKind::Unary(operator, right) => write!(f, "({} {})", operator.lexeme, right),
+            ExprKind::Grouping(expr) => write!(f, "(group {})", expr),
+            ExprKind::Variable(name) => write!(f, "{}", name.lexeme),
+            ExprKind::Assign(name, value) => write!(f, "(= {} {})", name.lexeme, value),
+            ExprKind::Logical(left, operator, right) => write!(f, "({} {} {})", operator.lexeme, left, right),
+            ExprKind::Call(callee, _, arguments, keywords) => {
+                write!(f, "(call {}", callee)?;
+                for argument in arguments.iter() {
+                    write!(f, " {}", argument)?;
+                }
+                for keyword in keywords.iter() {
+                    write!(f, " (key {} {})", keyword.name.lexeme, keyword.value)?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Get(object, name) => write!(f, "(. {} {})", object, name.lexeme),
+            ExprKind::List(elements) => {
+                write!(f, "(list")?;
+                for element in elements.iter() {
+                    write!(f, " {}", element)?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Index(object, _, index) => write!(f, "(index {} {})", object, index),
+            ExprKind::Object(properties) => {
+                write!(f, "(object")?;
+                for property in properties.iter() {
+                    write!(f, " ({} {})", property.name.lexeme, property.value)?;
+                }
+                write!(f, ")")
+            }
+            ExprKind::Destructure(pattern, value) => write!(f, "(= {} {})", pattern, value),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum UnaryOp {
-    Negate,
-    Not,   
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Name(name) => write!(f, "{}", name.lexeme),
+            Pattern::List(_, elements, rest) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                if let Some(rest) = rest {
+                    if !elements.is_empty() {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "...{}", rest.lexeme)?;
+                }
+                write!(f, "]")
+            }
+            Pattern::Object(_, fields) => {
+                write!(f, "{{")?;
+                for (i, (key, pattern)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    match pattern {
+                        Pattern::Name(name) if name.lexeme == key.lexeme => write!(f, "{}", key.lexeme)?,
+                        _ => write!(f, "{}: {}", key.lexeme, pattern)?,
+                    }
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Nil => write!(f, "nil"),
+            Literal::Boolean(val) => write!(f, "{}", val),
+            Literal::Number(val) => write!(f, "{}", val),
+            Literal::Str(val) => write!(f, "{}", val),
+            Literal::LoxCallable(lc) => write!(f, "{}", lc),
+            Literal::Namespace(ns) => write!(f, "{}", ns),
+            Literal::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Literal::Object(object) => write!(f, "{}", object.borrow()),
+        }
+    }
 }
 
 impl fmt::Display for LoxCallables {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             LoxCallables::LoxFunction(lc) => write!(f, "<fn {}>", lc.declaration.name.lexeme),
-            LoxCallables::LoxAnonymous(la) => write!(f, "<anonymous fn>"),
+            LoxCallables::LoxAnonymous(_) => write!(f, "<anonymous fn>"),
         }
     }
 }
 
-pub fn unescape(s: &str) -> Cow<str> {
+pub fn unescape(s: &str) -> Cow<'_, str> {
     Cow::Borrowed(s.trim_matches('"'))
 }
@@ -0,0 +1,422 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{environment::Environment, expr::{Expr, ExprKind}, stmt::{FunctionStmt, MatchPattern, Stmt, StmtKind}, token::Token};
+
+/// Warns about reads of variables that may not hold a value yet: a global
+/// read before its declaration has run, which fails at run time, and a
+/// variable declared without an initializer and read before any path
+/// assigns it, which silently yields nil.
+///
+/// The analysis follows the order statements run in. An `if` assigns a
+/// variable only when both branches do, a loop body may not run at all, a
+/// `return` ends its path and a match arm only runs when it matches. A
+/// function body runs when the function is called, so the globals it reads
+/// are checked at each call in top-level code instead of where the body is.
+///
+/// Names already bound in `globals` count as declared and assigned.
+pub fn analyze(stmts: &[Stmt], globals: &Environment) -> Vec<String> {
+    let mut flow = Flow {
+        variables: Vec::new(),
+        scopes: Vec::new(),
+        globals: HashMap::new(),
+        functions: Vec::new(),
+        summaries: HashMap::new(),
+        reassigned: HashSet::new(),
+        assigned_in_functions: HashSet::new(),
+        state: State::new(),
+        warnings: Vec::new(),
+    };
+
+    let visible = globals.visible();
+    let mut names = Vec::new();
+    global_names(stmts, &mut names);
+    for name in names {
+        if flow.globals.contains_key(&name.lexeme) {
+            continue;
+        }
+        let variable = flow.variables.len();
+        flow.variables.push(name.lexeme.clone());
+        flow.globals.insert(name.lexeme.clone(), variable);
+        if visible.iter().any(|(bound, ..)| *bound == name.lexeme) {
+            flow.state.declared.insert(variable);
+            flow.state.assigned.insert(variable);
+        }
+    }
+
+    for stmt in stmts {
+        flow.statement(stmt);
+    }
+    flow.warnings
+}
+
+// What is known to have happened on every path to the current point.
+#[derive(Clone)]
+struct State {
+    declared: HashSet<usize>,
+    assigned: HashSet<usize>,
+    reachable: bool,
+}
+
+impl State {
+    fn new() -> Self {
+        State { declared: HashSet::new(), assigned: HashSet::new(), reachable: true }
+    }
+
+    // Where two paths meet, only what happened on both holds.
+    fn join(self, other: State) -> State {
+        if !self.reachable {
+            return other;
+        }
+        if !other.reachable {
+            return self;
+        }
+        State {
+            declared: self.declared.intersection(&other.declared).copied().collect(),
+            assigned: self.assigned.intersection(&other.assigned).copied().collect(),
+            reachable: true,
+        }
+    }
+
+    fn unreachable() -> Self {
+        State { reachable: false, ..State::new() }
+    }
+}
+
+// What calling a function can do to globals.
+#[derive(Default)]
+struct Summary {
+    reads: HashSet<usize>,
+    calls: HashSet<usize>,
+}
+
+struct Flow {
+    // The name of each variable, indexed by the ids below.
+    variables: Vec<String>,
+    scopes: Vec<HashMap<String, usize>>,
+    globals: HashMap<String, usize>,
+    // For each function being analyzed, its own variable and the first
+    // variable declared inside it. Anything older is captured from outside.
+    functions: Vec<(usize, usize)>,
+    summaries: HashMap<usize, Summary>,
+    // Variables that were assigned after being declared, so a function they
+    // named may have been replaced.
+    reassigned: HashSet<usize>,
+    // Globals a function body assigns, which any call may have done.
+    assigned_in_functions: HashSet<usize>,
+    state: State,
+    warnings: Vec<String>,
+}
+
+impl Flow {
+    fn warn(&mut self, token: &Token, message: &str) {
+        let warning = format!("[line {}] Warning at '{}': {}", token.line, token.lexeme, message);
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+            .copied()
+    }
+
+    fn is_global(&self, variable: usize) -> bool {
+        self.globals.get(&self.variables[variable]) == Some(&variable)
+    }
+
+    fn declare(&mut self, name: &Token, assigned: bool) -> usize {
+        let variable = match self.scopes.last_mut() {
+            Some(scope) => {
+                let variable = self.variables.len();
+                self.variables.push(name.lexeme.clone());
+                scope.insert(name.lexeme.clone(), variable);
+                variable
+            }
+            None => self.globals[&name.lexeme],
+        };
+        self.state.declared.insert(variable);
+        // Declaring a global again without a value sets it back to nil.
+        match assigned {
+            true => self.state.assigned.insert(variable),
+            false => self.state.assigned.remove(&variable),
+        };
+        variable
+    }
+
+    fn read(&mut self, name: &Token) {
+        let Some(variable) = self.lookup(&name.lexeme) else {
+            return;
+        };
+        if let Some(&(function, start)) = self.functions.last() {
+            if self.is_global(variable) {
+                self.summaries.entry(function).or_default().reads.insert(variable);
+            }
+            if variable < start {
+                return;
+            }
+        }
+        if !self.state.reachable {
+            return;
+        }
+        if !self.state.declared.contains(&variable) {
+            self.warn(name, "Variable may be read before it is declared.");
+        }
+        else if !self.state.assigned.contains(&variable) && !self.assigned_in_functions.contains(&variable) {
+            self.warn(name, "Variable may be read before it is assigned.");
+        }
+    }
+
+    fn assign(&mut self, name: &Token) {
+        let Some(variable) = self.lookup(&name.lexeme) else {
+            return;
+        };
+        self.reassigned.insert(variable);
+        if let Some(&(_, start)) = self.functions.last() {
+            if self.is_global(variable) {
+                self.assigned_in_functions.insert(variable);
+            }
+            if variable < start {
+                return;
+            }
+        }
+        if self.state.reachable && !self.state.declared.contains(&variable) {
+            self.warn(name, "Variable may be assigned before it is declared.");
+        }
+        self.state.assigned.insert(variable);
+    }
+
+    // Checks the globals a call to `callee` reads, and those of every
+    // function it calls in turn, against what top-level code has done so far.
+    fn call(&mut self, callee: &Token) {
+        if !self.functions.is_empty() || !self.state.reachable {
+            return;
+        }
+        let Some(function) = self.lookup(&callee.lexeme) else {
+            return;
+        };
+
+        let mut pending = vec![function];
+        let mut seen = HashSet::new();
+        let mut reads = HashSet::new();
+        while let Some(function) = pending.pop() {
+            if !seen.insert(function) || self.reassigned.contains(&function) {
+                continue;
+            }
+            if let Some(summary) = self.summaries.get(&function) {
+                reads.extend(summary.reads.iter().copied());
+                pending.extend(summary.calls.iter().copied());
+            }
+        }
+
+        let mut reads: Vec<usize> = reads.into_iter().collect();
+        reads.sort();
+        for variable in reads {
+            let name = &self.variables[variable];
+            if !self.state.declared.contains(&variable) {
+                let message = format!("Call may read '{}' before it is declared.", name);
+                self.warn(callee, &message);
+            }
+            else if !self.state.assigned.contains(&variable) && !self.assigned_in_functions.contains(&variable) {
+                let message = format!("Call may read '{}' before it is assigned.", name);
+                self.warn(callee, &message);
+            }
+        }
+    }
+
+    fn scoped(&mut self, body: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        body(self);
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::ExprStmt(expr) | StmtKind::PrintStmt(expr) => self.expression(expr),
+            StmtKind::Declaration { name, initializer, .. } => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.declare(name, initializer.is_some());
+            }
+            StmtKind::Destructure { pattern, initializer, .. } => {
+                self.expression(initializer);
+                for name in pattern.names() {
+                    self.declare(name, true);
+                }
+            }
+            StmtKind::Block(stmts) => self.scoped(|flow| {
+                for stmt in stmts.iter() {
+                    flow.statement(stmt);
+                }
+            }),
+            StmtKind::Function(fun_stmt) => self.function(fun_stmt),
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                let before = self.state.clone();
+                self.statement(then_branch);
+                let then_state = std::mem::replace(&mut self.state, before);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+                self.state = then_state.join(std::mem::replace(&mut self.state, State::new()));
+            }
+            // The body may run no times at all.
+            StmtKind::While(condition, body) => {
+                self.expression(condition);
+                let before = self.state.clone();
+                self.statement(body);
+                self.state = before;
+            }
+            StmtKind::DoWhile(body, condition) => {
+                self.statement(body);
+                self.expression(condition);
+            }
+            StmtKind::ForIn(name, iterable, body) => {
+                self.expression(iterable);
+                let before = self.state.clone();
+                self.scoped(|flow| {
+                    flow.declare(name, true);
+                    flow.statement(body);
+                });
+                self.state = before;
+            }
+            StmtKind::Return(_, value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                self.state.reachable = false;
+            }
+            StmtKind::Import(import) => {
+                for name in import.alias.iter().chain(import.names.iter()) {
+                    self.declare(name, true);
+                }
+            }
+            // A value no arm matches is a runtime error, so some arm always runs.
+            StmtKind::Match(match_stmt) => {
+                self.expression(&match_stmt.subject);
+                let before = self.state.clone();
+                let mut after = State::unreachable();
+                for arm in &match_stmt.arms {
+                    self.state = before.clone();
+                    self.scoped(|flow| {
+                        if let MatchPattern::Binding(name) = &arm.pattern {
+                            flow.declare(name, true);
+                        }
+                        if let Some(guard) = &arm.guard {
+                            flow.expression(guard);
+                        }
+                        flow.statement(&arm.body);
+                    });
+                    after = after.join(std::mem::replace(&mut self.state, State::new()));
+                }
+                self.state = after;
+            }
+        }
+    }
+
+    fn function(&mut self, fun_stmt: &FunctionStmt) {
+        let function = self.declare(&fun_stmt.name, true);
+        // Declaring a function again replaces it, so the last one is what a call runs.
+        self.summaries.remove(&function);
+        self.functions.push((function, self.variables.len()));
+        let outer = std::mem::replace(&mut self.state, State::new());
+
+        self.scoped(|flow| {
+            for param in &fun_stmt.params {
+                if let Some(default) = &param.default {
+                    flow.expression(default);
+                }
+                flow.declare(&param.name, true);
+            }
+            for stmt in &fun_stmt.body {
+                flow.statement(stmt);
+            }
+        });
+
+        self.state = outer;
+        self.functions.pop();
+        // A function declared inside another one runs when that one does.
+        if let Some(&(enclosing, _)) = self.functions.last() {
+            self.summaries.entry(enclosing).or_default().calls.insert(function);
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lit(_) => {}
+            ExprKind::Variable(name) => self.read(name),
+            ExprKind::Grouping(inner) | ExprKind::Unary(_, inner) | ExprKind::Get(inner, _) => self.expression(inner),
+            ExprKind::Binary(left, _, right) | ExprKind::Index(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            // The right operand doesn't always run.
+            ExprKind::Logical(left, _, right) => {
+                self.expression(left);
+                let before = self.state.clone();
+                self.expression(right);
+                self.state = before;
+            }
+            ExprKind::Assign(name, value) => {
+                self.expression(value);
+                self.assign(name);
+            }
+            ExprKind::Destructure(pattern, value) => {
+                self.expression(value);
+                for name in pattern.names() {
+                    self.assign(name);
+                }
+            }
+            ExprKind::Call(callee, _, arguments, keywords) => {
+                self.expression(callee);
+                for argument in arguments.iter() {
+                    self.expression(argument);
+                }
+                for keyword in keywords.iter() {
+                    self.expression(&keyword.value);
+                }
+                if let ExprKind::Variable(name) = &callee.kind {
+                    match self.functions.last() {
+                        Some(&(function, _)) => {
+                            if let Some(callee) = self.lookup(&name.lexeme) {
+                                self.summaries.entry(function).or_default().calls.insert(callee);
+                            }
+                        }
+                        None => self.call(name),
+                    }
+                }
+            }
+            ExprKind::List(elements) => {
+                for element in elements.iter() {
+                    self.expression(element);
+                }
+            }
+            ExprKind::Object(properties) => {
+                for property in properties.iter() {
+                    self.expression(&property.value);
+                }
+            }
+        }
+    }
+}
+
+// The names declared in the global scope: those outside any block, function,
+// loop or match arm.
+fn global_names<'a>(stmts: impl IntoIterator<Item = &'a Stmt>, names: &mut Vec<&'a Token>) {
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::Declaration { name, .. } => names.push(name),
+            StmtKind::Destructure { pattern, .. } => names.extend(pattern.names()),
+            StmtKind::Function(fun_stmt) => names.push(&fun_stmt.name),
+            StmtKind::Import(import) => names.extend(import.alias.iter().chain(import.names.iter())),
+            StmtKind::If(_, then_branch, else_branch) => {
+                global_names([&**then_branch], names);
+                global_names(else_branch.as_deref(), names);
+            }
+            StmtKind::While(_, body) | StmtKind::DoWhile(body, _) => global_names([&**body], names),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use std::{collections::HashMap, fmt, fs};
+
+use crate::{expr::{Expr, ExprKind}, stmt::{FunctionStmt, MatchPattern, Stmt, StmtKind}, token::{Token, TokenType}};
+
+/// Where lint settings are read from when no `--config=` is given.
+pub const DEFAULT_CONFIG: &str = ".loxlint";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    UnusedVariable,
+    UnusedParameter,
+    UnusedFunction,
+    UnreachableCode,
+    Shadowing,
+    SelfComparison,
+    EmptyBlock,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::UnusedVariable,
+        Rule::UnusedParameter,
+        Rule::UnusedFunction,
+        Rule::UnreachableCode,
+        Rule::Shadowing,
+        Rule::SelfComparison,
+        Rule::EmptyBlock,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::UnusedParameter => "unused-parameter",
+            Rule::UnusedFunction => "unused-function",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::Shadowing => "shadowing",
+            Rule::SelfComparison => "self-comparison",
+            Rule::EmptyBlock => "empty-block",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.id() == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Off,
+    Warning,
+    Error,
+}
+
+/// Which rules run and how loudly. Every rule starts out as a warning.
+pub struct Config {
+    levels: HashMap<Rule, Level>,
+    // Whether the file is a module, whose top-level names may be used by
+    // whatever imports it rather than by the file itself.
+    pub module: bool,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Config { levels: Rule::ALL.into_iter().map(|rule| (rule, Level::Warning)).collect(), module: false }
+    }
+
+    pub fn level(&self, rule: Rule) -> Level {
+        self.levels[&rule]
+    }
+
+    /// Sets each rule in a comma separated list of ids.
+    pub fn set(&mut self, ids: &str, level: Level) -> Result<(), String> {
+        for rule in Self::rules(ids)? {
+            self.levels.insert(rule, level);
+        }
+        Ok(())
+    }
+
+    /// Turns on each rule in a comma separated list of ids that is off,
+    /// leaving rules already reported as errors at that level.
+    pub fn enable(&mut self, ids: &str) -> Result<(), String> {
+        for rule in Self::rules(ids)? {
+            if self.levels[&rule] == Level::Off {
+                self.levels.insert(rule, Level::Warning);
+            }
+        }
+        Ok(())
+    }
+
+    fn rules(ids: &str) -> Result<Vec<Rule>, String> {
+        ids.split(',').map(str::trim).filter(|id| !id.is_empty())
+            .map(|id| Rule::from_id(id).ok_or_else(|| format!("Unknown lint rule '{}'.", id)))
+            .collect()
+    }
+
+    /// Applies a config file made of `rule-id = off|warn|error` lines, where
+    /// `#` starts a comment.
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("Can't read lint config '{}': {}.", path, e))?;
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = || format!("{}:{}: Expect 'rule-id = off|warn|error'.", path, number + 1);
+            let (id, level) = line.split_once('=').ok_or_else(error)?;
+            let level = match level.trim() {
+                "off" => Level::Off,
+                "warn" => Level::Warning,
+                "error" => Level::Error,
+                _ => return Err(error()),
+            };
+            self.set(id, level).map_err(|e| format!("{}:{}: {}", path, number + 1, e))?;
+        }
+        Ok(())
+    }
+
+    /// Builds the config from the `lint` command's flags: `--config=<file>`
+    /// (otherwise `.loxlint`, if there is one), then `--enable=`,
+    /// `--disable=` and `--error=`, which take precedence over the file.
+    /// `--module` leaves unused top-level names unreported. Any other flag is
+    /// an error.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = Config::new();
+
+        match args.iter().find_map(|arg| arg.strip_prefix("--config=")) {
+            Some(path) => config.load(path)?,
+            None if fs::metadata(DEFAULT_CONFIG).is_ok() => config.load(DEFAULT_CONFIG)?,
+            None => {}
+        }
+
+        for arg in args {
+            if let Some(ids) = arg.strip_prefix("--enable=") {
+                config.enable(ids)?;
+            }
+            else if let Some(ids) = arg.strip_prefix("--disable=") {
+                config.set(ids, Level::Off)?;
+            }
+            else if let Some(ids) = arg.strip_prefix("--error=") {
+                config.set(ids, Level::Error)?;
+            }
+            else if arg == "--module" {
+                config.module = true;
+            }
+            else if arg.starts_with("--") && !arg.starts_with("--config=") {
+                return Err(format!("Unknown lint option '{}'.", arg));
+            }
+        }
+        Ok(config)
+    }
+}
+
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub level: Level,
+    pub line: usize,
+    // The token the problem is at, when there is a single one to point to.
+    pub at: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = if self.level == Level::Error { "Error" } else { "Warning" };
+        match &self.at {
+            Some(at) => write!(f, "[line {}] {} at '{}': {} [{}]", self.line, level, at, self.message, self.rule.id()),
+            None => write!(f, "[line {}] {}: {} [{}]", self.line, level, self.message, self.rule.id()),
+        }
+    }
+}
+
+enum Kind {
+    Variable,
+    Parameter,
+    Function,
+}
+
+struct Binding {
+    name: Token,
+    kind: Kind,
+    used: bool,
+}
+
+/// Checks a parsed program, returning what the enabled rules found in line order.
+pub fn lint(stmts: &[Stmt], config: &Config) -> Vec<Diagnostic> {
+    let mut linter = Linter { config, scopes: vec![Vec::new()], diagnostics: Vec::new() };
+    linter.statements(stmts);
+    if config.module {
+        linter.scopes.pop();
+    }
+    else {
+        linter.end_scope();
+    }
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    diagnostics
+}
+
+struct Linter<'a> {
+    config: &'a Config,
+    scopes: Vec<Vec<Binding>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: Rule, line: usize, at: Option<&str>, message: String) {
+        let level = self.config.level(rule);
+        if level == Level::Off {
+            return;
+        }
+        self.diagnostics.push(Diagnostic { rule, level, line, at: at.map(str::to_string), message });
+    }
+
+    fn report_at(&mut self, rule: Rule, token: &Token, message: String) {
+        self.report(rule, token.line, Some(&token.lexeme), message);
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for binding in scope.into_iter().filter(|binding| !binding.used) {
+            let name = &binding.name.lexeme;
+            match binding.kind {
+                Kind::Variable => self.report_at(Rule::UnusedVariable, &binding.name, format!("Variable '{}' is never used.", name)),
+                Kind::Parameter => self.report_at(Rule::UnusedParameter, &binding.name, format!("Parameter '{}' is never used.", name)),
+                Kind::Function => self.report_at(Rule::UnusedFunction, &binding.name, format!("Function '{}' is never called.", name)),
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, kind: Kind) {
+        // A leading underscore marks a name as deliberately unused.
+        let ignored = name.lexeme.starts_with('_');
+        let (inner, outer) = self.scopes.split_last().expect("the top-level scope is always open");
+        if !ignored && !inner.iter().any(|binding| binding.name.lexeme == name.lexeme) {
+            if let Some(shadowed) = outer.iter().flatten().rev().find(|binding| binding.name.lexeme == name.lexeme) {
+                let message = format!("'{}' shadows the declaration on line {}.", name.lexeme, shadowed.name.line);
+                self.report_at(Rule::Shadowing, name, message);
+            }
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Binding { name: name.clone(), kind, used: ignored });
+        }
+    }
+
+    fn use_name(&mut self, name: &Token) {
+        let binding = self.scopes.iter_mut().rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|binding| binding.name.lexeme == name.lexeme));
+        if let Some(binding) = binding {
+            binding.used = true;
+        }
+    }
+
+    fn statements(&mut self, stmts: &[Stmt]) {
+        // Only the first dead statement is reported, but all of them are
+        // still checked so the names they use count as used.
+        if let Some(dead) = stmts.iter().position(always_returns).and_then(|i| stmts.get(i + 1)) {
+            self.report(Rule::UnreachableCode, dead.span.line, None, "Unreachable code after 'return'.".to_string());
+        }
+        // Functions are declared up front, so a body can call one declared
+        // further down the same scope.
+        for stmt in stmts {
+            if let StmtKind::Function(fun_stmt) = &stmt.kind {
+                self.declare(&fun_stmt.name, Kind::Function);
+            }
+        }
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::ExprStmt(expr) | StmtKind::PrintStmt(expr) => self.expression(expr),
+            StmtKind::Declaration { name, initializer, .. } => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.declare(name, Kind::Variable);
+            }
+            StmtKind::Destructure { pattern, initializer, .. } => {
+                self.expression(initializer);
+                for name in pattern.names() {
+                    self.declare(name, Kind::Variable);
+                }
+            }
+            StmtKind::Block(stmts) => {
+                if stmts.is_empty() {
+                    self.report(Rule::EmptyBlock, stmt.span.line, Some("{"), "Empty block.".to_string());
+                }
+                self.begin_scope();
+                self.statements(stmts);
+                self.end_scope();
+            }
+            StmtKind::Function(fun_stmt) => self.function(fun_stmt),
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::While(condition, body) => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            StmtKind::DoWhile(body, condition) => {
+                self.statement(body);
+                self.expression(condition);
+            }
+            StmtKind::ForIn(name, iterable, body) => {
+                self.expression(iterable);
+                self.begin_scope();
+                self.declare(name, Kind::Variable);
+                self.statement(body);
+                self.end_scope();
+            }
+            StmtKind::Return(_, value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            StmtKind::Import(import) => {
+                for name in import.alias.iter().chain(import.names.iter()) {
+                    self.declare(name, Kind::Variable);
+                }
+            }
+            StmtKind::Match(match_stmt) => {
+                self.expression(&match_stmt.subject);
+                for arm in &match_stmt.arms {
+                    self.begin_scope();
+                    if let MatchPattern::Binding(name) = &arm.pattern {
+                        self.declare(name, Kind::Variable);
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.statement(&arm.body);
+                    self.end_scope();
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, fun_stmt: &FunctionStmt) {
+        self.begin_scope();
+        for param in &fun_stmt.params {
+            if let Some(default) = &param.default {
+                self.expression(default);
+            }
+            self.declare(&param.name, Kind::Parameter);
+        }
+        self.statements(&fun_stmt.body);
+        self.end_scope();
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lit(_) => {}
+            ExprKind::Grouping(inner) | ExprKind::Unary(_, inner) => self.expression(inner),
+            ExprKind::Binary(left, operator, right) => {
+                if is_comparison(&operator.token_type) && same_value(left, right) {
+                    self.report_at(Rule::SelfComparison, operator, format!("Both sides of '{}' are the same.", operator.lexeme));
+                }
+                self.expression(left);
+                self.expression(right);
+            }
+            ExprKind::Logical(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExprKind::Variable(name) => self.use_name(name),
+            // Writing to a variable isn't a use of it.
+            ExprKind::Assign(_, value) | ExprKind::Destructure(_, value) => self.expression(value),
+            ExprKind::Call(callee, _, arguments, keywords) => {
+                self.expression(callee);
+                for argument in arguments.iter() {
+                    self.expression(argument);
+                }
+                for keyword in keywords.iter() {
+                    self.expression(&keyword.value);
+                }
+            }
+            ExprKind::Get(object, _) => self.expression(object),
+            ExprKind::Index(object, _, index) => {
+                self.expression(object);
+                self.expression(index);
+            }
+            ExprKind::List(elements) => {
+                for element in elements.iter() {
+                    self.expression(element);
+                }
+            }
+            ExprKind::Object(properties) => {
+                for property in properties.iter() {
+                    self.expression(&property.value);
+                }
+            }
+        }
+    }
+}
+
+fn is_comparison(token_type: &TokenType) -> bool {
+    matches!(token_type,
+        TokenType::EqualEqual | TokenType::BangEqual | TokenType::Less | TokenType::LessEqual | TokenType::Greater | TokenType::GreaterEqual)
+}
+
+// Whether `left` and `right` are written the same way and evaluating them is
+// sure to give the same value.
+fn same_value(left: &Expr, right: &Expr) -> bool {
+    match (&left.kind, &right.kind) {
+        (ExprKind::Lit(left), ExprKind::Lit(right)) => left == right,
+        (ExprKind::Variable(left), ExprKind::Variable(right)) => left.lexeme == right.lexeme,
+        (ExprKind::Grouping(left), ExprKind::Grouping(right)) => same_value(left, right),
+        (ExprKind::Get(left, left_name), ExprKind::Get(right, right_name)) => left_name.lexeme == right_name.lexeme && same_value(left, right),
+        (ExprKind::Index(left, _, left_index), ExprKind::Index(right, _, right_index)) => same_value(left, right) && same_value(left_index, right_index),
+        _ => false,
+    }
+}
+
+fn always_returns(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::Return(..) => true,
+        StmtKind::Block(stmts) => stmts.iter().any(always_returns),
+        StmtKind::If(_, then_branch, Some(else_branch)) => always_returns(then_branch) && always_returns(else_branch),
+        _ => false,
+    }
+}
@@ -1,7 +1,8 @@
-use crate::{evaluator::{Evaluator, RuntimeException}, expr::Literal};
+use crate::{evaluator::{Evaluator, RuntimeException}, expr::Literal, token::Token};
 
 pub trait LoxCallable {
-    fn callq(&self, evaluator: &mut Evaluator, arguments: Vec<Literal>) -> Result<Option<Box<Literal>>, RuntimeException>;
+    fn callq(&self, evaluator: &mut Evaluator, arguments: Vec<Literal>, keywords: Vec<(Token, Literal)>) -> Result<Option<Box<Literal>>, RuntimeException>;
     fn arrity(&self) -> usize;
-    fn to_string(&self) -> String;
+    // `None` when the callable takes any number of trailing arguments.
+    fn max_arrity(&self) -> Option<usize>;
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
-use std::{cell::{Ref, RefCell}, rc::Rc};
+use std::{cell::RefCell, rc::Rc};
 
-use crate::{environment::Environment, evaluator::{Evaluator, RuntimeException}, expr::{Literal, LoxCallables}, lox_callable::LoxCallable, stmt::FunctionStmt};
+use crate::{environment::Environment, evaluator::{Evaluator, RuntimeError, RuntimeException}, expr::{Literal, LoxCallables}, lox_callable::LoxCallable, stmt::FunctionStmt, token::Token};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct LoxFunction {
@@ -8,15 +8,16 @@ pub struct LoxFunction {
     pub closure: Rc<RefCell<Environment>>,
 }
 
+#[allow(unpredictable_function_pointer_comparisons)]
 #[derive(Clone, PartialEq, Debug)]
 pub struct LoxAnonymous {
-    callq: fn(
-        &mut Evaluator,
-        Vec<Literal>,
-    ) -> Result<Option<Box<Literal>>, RuntimeException>,
+    callq: Native,
     arrity: fn() -> usize,
 }
 
+/// What a native function runs when called.
+pub type Native = fn(&mut Evaluator, Vec<Literal>) -> Result<Option<Box<Literal>>, RuntimeException>;
+
 impl LoxFunction {
     pub fn new(declaration: FunctionStmt, closure: Rc<RefCell<Environment>>) -> Self {
         LoxFunction { declaration, closure }
@@ -24,10 +25,15 @@ impl LoxFunction {
 }
 
 impl LoxCallable for LoxCallables {
-    fn callq(&self, evaluator: &mut Evaluator, arguments: Vec<Literal>) -> Result<Option<Box<Literal>>, RuntimeException> {
+    fn callq(&self, evaluator: &mut Evaluator, arguments: Vec<Literal>, keywords: Vec<(Token, Literal)>) -> Result<Option<Box<Literal>>, RuntimeException> {
         match self {
-            LoxCallables::LoxFunction(lc) => lc.callq(evaluator, arguments),
-            LoxCallables::LoxAnonymous(la) => (la.callq)(evaluator, arguments),
+            LoxCallables::LoxFunction(lc) => lc.callq(evaluator, arguments, keywords),
+            LoxCallables::LoxAnonymous(la) => {
+                if let Some((name, _)) = keywords.first() {
+                    return Err(RuntimeException::RuntimeError(RuntimeError::new(name, "Native functions don't accept keyword arguments.")));
+                }
+                (la.callq)(evaluator, arguments)
+            }
         }
     }
 
@@ -38,37 +44,68 @@ impl LoxCallable for LoxCallables {
         }
     }
 
-    fn to_string(&self) -> String {
-        todo!()
+    fn max_arrity(&self) -> Option<usize> {
+        match self {
+            LoxCallables::LoxFunction(lc) => lc.max_arrity(),
+            LoxCallables::LoxAnonymous(la) => Some((la.arrity)()),
+        }
     }
 }
 
 impl LoxCallable for LoxFunction {
-    fn callq(&self, evaluator: &mut Evaluator, arguments: Vec<Literal>) -> Result<Option<Box<Literal>>, RuntimeException> {
-        let mut env = Environment::new(Some(evaluator.globals.clone()));
-        for i in 0..self.declaration.params.len() {
-            env.define(self.declaration.params[i].clone().lexeme, Some(Box::from(arguments[i].clone())));
+    fn callq(&self, evaluator: &mut Evaluator, arguments: Vec<Literal>, mut keywords: Vec<(Token, Literal)>) -> Result<Option<Box<Literal>>, RuntimeException> {
+        let names: Vec<&Token> = keywords.iter().map(|(name, _)| name).collect();
+        self.declaration.check_keywords(arguments.len(), &names)
+            .map_err(|(name, message)| RuntimeException::RuntimeError(RuntimeError::new(name, &message)))?;
+
+        let env = Rc::new(RefCell::new(Environment::local(self.closure.clone())));
+        let call_site = keywords.first().map(|(name, _)| name.clone());
+        let mut arguments = arguments.into_iter();
+
+        for param in &self.declaration.params {
+            let keyword = keywords.iter()
+                .position(|(name, _)| name.lexeme == param.name.lexeme)
+                .map(|i| keywords.swap_remove(i).1);
+
+            let value = if param.rest {
+                Literal::List(Rc::new(RefCell::new(arguments.by_ref().collect())))
+            }
+            else if let Some(argument) = arguments.next().or(keyword) {
+                argument
+            }
+            else if let Some(default) = &param.default {
+                // Defaults see the parameters bound before them.
+                *evaluator.evaluate_in(default, env.clone())?
+            }
+            else {
+                let token = call_site.as_ref().unwrap_or(&param.name);
+                return Err(RuntimeException::RuntimeError(RuntimeError::new(token, &format!("Missing argument for parameter '{}'.", param.name.lexeme))));
+            };
+
+            if param.constant {
+                env.borrow_mut().define_const(param.name.lexeme.clone(), Some(Box::from(value)));
+            }
+            else {
+                env.borrow_mut().define(param.name.lexeme.clone(), Some(Box::from(value)));
+            }
         }
 
-        return evaluator.execute_block(&self.declaration.body, Rc::from(RefCell::from(env)))
-                        .map(|_| None);
+        evaluator.execute_block(&self.declaration.body, env)
+                        .map(|_| None)
     }
 
     fn arrity(&self) -> usize {
-        self.declaration.params.len()
+        self.declaration.arity()
     }
 
-    fn to_string(&self) -> String {
-        return format!("<fn {} >", self.declaration.name.lexeme);
+    fn max_arrity(&self) -> Option<usize> {
+        self.declaration.max_arity()
     }
 }
 
 impl LoxAnonymous {
     pub fn new(
-        callq: fn(
-            &mut Evaluator,
-            Vec<Literal>,
-        ) -> Result<Option<Box<Literal>>, RuntimeException>,
+        callq: Native,
         arrity: fn() -> usize,
     ) -> LoxAnonymous {
         LoxAnonymous {
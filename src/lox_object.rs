@@ -0,0 +1,44 @@
+use std::fmt;
+
+use crate::expr::Literal;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LoxObject {
+    fields: Vec<(String, Literal)>,
+}
+
+impl LoxObject {
+    pub fn new() -> Self {
+        LoxObject { fields: Vec::new() }
+    }
+
+    pub fn fields(&self) -> &[(String, Literal)] {
+        &self.fields
+    }
+
+    pub fn get(&self, name: &str) -> Option<Literal> {
+        self.fields.iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn set(&mut self, name: String, value: Literal) {
+        match self.fields.iter_mut().find(|(field, _)| *field == name) {
+            Some((_, slot)) => *slot = value,
+            None => self.fields.push((name, value)),
+        }
+    }
+}
+
+impl fmt::Display for LoxObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, (name, value)) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", name, value)?;
+        }
+        write!(f, "}}")
+    }
+}
@@ -8,100 +8,247 @@ mod stmt;
 mod environment;
 mod lox_callable;
 mod lox_function;
+mod lox_object;
+mod module;
+mod ast_json;
+mod repl;
+mod unparser;
+mod resolver;
+mod lint;
+mod optimizer;
+mod callgraph;
+mod checker;
+mod flow;
+mod error;
+mod scopes;
 
-use std::cell::RefCell;
 use std::env;
 use std::fs;
 use std::fmt::Write;
+use std::path::Path;
 use std::process;
-use std::rc::Rc;
-use environment::Environment;
+use std::thread;
+use error::{LoxError, ParseError};
 use parser::Parser;
-use evaluator::Evaluator;
+use evaluator::{Evaluator, RuntimeException};
 use scanner::Scanner;
 
+// The evaluator recurses for every Lox call, and a debug build can use well
+// over 10KB of stack per call, so the interpreter gets a stack with room for
+// `evaluator::DEFAULT_MAX_DEPTH` calls many times over.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+// A debug build runs out of `STACK_SIZE` somewhere past 8000 calls, so
+// `--max-depth` is held to half that.
+const MAX_DEPTH: usize = 4096;
+
 fn main() {
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("Can't start the interpreter thread.");
+    // A panic has already been reported by the thread that had it.
+    if interpreter.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn run() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        eprintln!("Usage: <command> <filename>");
-        process::exit(1);
+    if args.get(1).is_some_and(|command| command == "repl") {
+        repl::Repl::new().run();
+        return;
     }
 
-    let command = &args[1];
-    let filename = &args[2];
+    let json = args.iter().skip(2).any(|arg| arg == "--json");
+    let filename = args.iter().skip(2).find(|arg| !arg.starts_with("--"));
+    let (Some(command), Some(filename)) = (args.get(1), filename) else {
+        eprintln!("Usage: <command> [--json] [--max-depth=N] <filename>, or repl");
+        process::exit(1);
+    };
 
     let file_contents = fs::read_to_string(filename).unwrap_or_default();
     
     match command.as_str() {
         "tokenize" => {
-            let mut tokenizer = Scanner::new(&file_contents);
-            let tokens = tokenizer.scan_tokens();
+            let tokenizer = scan(&file_contents);
             let mut buffer = String::new();
-            for tok in tokens {
+            for tok in &tokenizer.tokens {
                 writeln!(buffer, "{}", tok).unwrap();
             }
             print!("{buffer}");
-            if tokenizer.code != 0 {
-                process::exit(tokenizer.code);
+            if let Some(e) = tokenizer.errors.first() {
+                process::exit(exit_code(&e.clone().into()));
             }
         },
         "parse" => {
-            let mut tokenizer = Scanner::new(&file_contents);
-            let tokens = tokenizer.scan_tokens();
+            let tokenizer = scan(&file_contents);
+            let tokens = &tokenizer.tokens;
             let mut parser = Parser::new(tokens);
-            let res = parser.parse();
-            match res {
-                Ok(expr) => println!("{}", expr),
+            match parser._parse() {
+                Ok(stmts) => {
+                    for warning in &parser.warnings {
+                        eprintln!("{warning}");
+                    }
+                    if json {
+                        println!("{:#}", ast_json::to_json(&stmts));
+                        return;
+                    }
+                    for stmt in stmts {
+                        println!("{}", stmt);
+                    }
+                }
+                // Not a program; it may still be a lone expression like `1 + 2`.
+                Err(e) => match Parser::new(tokens).parse() {
+                    Ok(expr) => println!("{}", expr),
+                    Err(_) => fail(ParseError(e)),
+                },
+            }
+        },
+        "unparse" => {
+            let tokenizer = scan(&file_contents);
+            let tokens = &tokenizer.tokens;
+            let mut parser = Parser::new(tokens);
+            match parser._parse() {
+                Ok(stmts) => print!("{}", unparser::unparse(&stmts)),
+                Err(e) => fail(ParseError(e)),
+            }
+        },
+        "lint" => {
+            let config = match lint::Config::from_args(&args[2..]) {
+                Ok(config) => config,
                 Err(e) => {
-                    eprintln!("{}", e);
-                    process::exit(65);
+                    eprintln!("{e}");
+                    process::exit(1);
                 }
+            };
+            let tokenizer = scan(&file_contents);
+            let tokens = &tokenizer.tokens;
+            let mut parser = Parser::new(tokens);
+            let stmts = match parser._parse() {
+                Ok(stmts) => stmts,
+                Err(e) => fail(ParseError(e)),
+            };
+
+            let diagnostics = lint::lint(&stmts, &config);
+            for diagnostic in &diagnostics {
+                eprintln!("{diagnostic}");
+            }
+            if diagnostics.iter().any(|diagnostic| diagnostic.level == lint::Level::Error) {
+                process::exit(65);
+            }
+        },
+        "callgraph" => {
+            let tokenizer = scan(&file_contents);
+            let tokens = &tokenizer.tokens;
+            let mut parser = Parser::new(tokens);
+            match parser._parse() {
+                Ok(stmts) => {
+                    let graph = callgraph::CallGraph::new(&stmts);
+                    if json {
+                        println!("{:#}", graph.to_json());
+                    }
+                    else {
+                        println!("{}", graph.to_dot());
+                    }
+                }
+                Err(e) => fail(ParseError(e)),
             }
         },
         "evaluate" => {
-            let env = Environment::new(None);
-            let p_env = Rc::from(RefCell::from(env));
-            let mut a = Evaluator::new(p_env);
-            let mut tokenizer = Scanner::new(&file_contents);
-            let tokens = tokenizer.scan_tokens();
+            let mut a = Evaluator::new();
+            let tokenizer = scan(&file_contents);
+            let tokens = &tokenizer.tokens;
             let mut parser = Parser::new(tokens);
             let res = parser.parse();
             match res {
                 Ok(expr) => {
-                    let result = a.evaluate(&expr);
-                    match result {
+                    match a.evaluate(&expr) {
                         Ok(_tw) => a.writer(&_tw),
-                        Err(e) => {
-                            eprintln!("{}", e);
-                            process::exit(70);
-                        }
+                        Err(RuntimeException::RuntimeError(e)) => fail(e),
+                        // A lone expression can't return from anything.
+                        Err(_) => {}
                     }
                 },
-                Err(e) => {
-                    eprintln!("{}", e);
-                    process::exit(65);
-                }
+                Err(e) => fail(ParseError(e)),
             }
         }
         "run" => {
-            let env = Environment::new(None);
-            let p_env = Rc::from(RefCell::from(env));
-            let mut a = Evaluator::new(p_env);
-            let mut tokenizer = Scanner::new(&file_contents);
-            let tokens = tokenizer.scan_tokens();
-            let mut parser = Parser::new(tokens);
-            let stmts = parser._parse();
-
-            if let Err(e) = stmts {
-                eprintln!("{e}");
-                process::exit(65);
+            let mut a = Evaluator::new();
+            a.set_script(Path::new(filename));
+            if let Some(depth) = args.iter().find_map(|arg| arg.strip_prefix("--max-depth=")) {
+                match depth.parse() {
+                    Ok(depth) if depth <= MAX_DEPTH => a.set_max_depth(depth),
+                    Ok(_) => {
+                        eprintln!("--max-depth can be at most {MAX_DEPTH}.");
+                        process::exit(1);
+                    }
+                    Err(_) => {
+                        eprintln!("Invalid --max-depth '{depth}'.");
+                        process::exit(1);
+                    }
+                }
+            }
+            let stmts = if json {
+                serde_json::from_str(&file_contents)
+                    .map_err(|e| format!("Invalid AST JSON: {e}"))
+                    .and_then(|value| ast_json::from_json(&value))
             }
+            else {
+                let tokenizer = scan(&file_contents);
+                let tokens = &tokenizer.tokens;
+                let mut parser = Parser::new(tokens);
+                let stmts = parser._parse();
+                for warning in &parser.warnings {
+                    eprintln!("{warning}");
+                }
+                // A program with characters the scanner skipped isn't the one that was written.
+                if let Some(e) = tokenizer.errors.first() {
+                    process::exit(exit_code(&e.clone().into()));
+                }
+                stmts
+            };
 
-            let st = stmts.unwrap();
+            let st = stmts.unwrap_or_else(|e| fail(ParseError(e)));
+            if let Err(e) = a.resolve(&st) {
+                fail(ParseError(e));
+            }
+            for warning in checker::check(&st, &a.globals.borrow()).into_iter().chain(flow::analyze(&st, &a.globals.borrow())) {
+                eprintln!("{warning}");
+            }
+            let st = optimizer::optimize(st);
 
-            let _ = a.interpret(st);
-            
+            if let Err(e) = a.interpret(st) {
+                fail(e);
+            }
         }
         _ => {}
     }
 }
+
+// Scans `source`, reporting anything that isn't a token. Later stages still
+// run over the tokens that were found.
+fn scan(source: &str) -> Scanner<'_> {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens();
+    for e in &scanner.errors {
+        eprintln!("{e}");
+    }
+    scanner
+}
+
+/// The exit code for an error that ends a run: 65 when the program doesn't
+/// compile, 70 when it fails while running.
+fn exit_code(error: &LoxError) -> i32 {
+    match error {
+        LoxError::Lex(_) | LoxError::Parse(_) => 65,
+        LoxError::Runtime(_) => 70,
+    }
+}
+
+// Reports an error that ends the run and exits with its code.
+fn fail(error: impl Into<LoxError>) -> ! {
+    let error = error.into();
+    eprintln!("{error}");
+    process::exit(exit_code(&error));
+}
@@ -0,0 +1,96 @@
+use std::{cell::RefCell, collections::HashMap, env, fmt, path::{Path, PathBuf}, rc::Rc};
+
+use crate::environment::Environment;
+
+#[derive(Debug, PartialEq)]
+pub struct Namespace {
+    pub name: String,
+    pub env: Rc<RefCell<Environment>>,
+}
+
+impl Namespace {
+    pub fn new(name: String, env: Rc<RefCell<Environment>>) -> Self {
+        Namespace { name, env }
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+/// Finds module files and remembers the ones that already ran.
+///
+/// `loading` is the chain of files currently executing, outermost first. Its last
+/// entry is the file relative paths are resolved against, and it is what gets
+/// reported when an import would loop back into a file that hasn't finished yet.
+pub struct ModuleLoader {
+    search_path: Vec<PathBuf>,
+    cache: HashMap<PathBuf, Rc<Namespace>>,
+    loading: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn new() -> Self {
+        let search_path = env::var_os("LOX_PATH")
+            .map(|paths| env::split_paths(&paths).collect())
+            .unwrap_or_default();
+
+        ModuleLoader {
+            search_path,
+            cache: HashMap::new(),
+            loading: Vec::new(),
+        }
+    }
+
+    pub fn set_script(&mut self, script: &Path) {
+        let script = script.canonicalize().unwrap_or_else(|_| script.to_path_buf());
+        self.loading = vec![script];
+    }
+
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let base = self.loading.last()
+            .and_then(|current| current.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        std::iter::once(base)
+            .chain(self.search_path.iter().cloned())
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.is_file())
+            .and_then(|found| found.canonicalize().ok())
+    }
+
+    pub fn cached(&self, path: &Path) -> Option<Rc<Namespace>> {
+        self.cache.get(path).cloned()
+    }
+
+    pub fn cycle(&self, path: &Path) -> Option<String> {
+        let start = self.loading.iter().position(|loading| loading == path)?;
+        let chain: Vec<String> = self.loading[start..].iter()
+            .chain(std::iter::once(&path.to_path_buf()))
+            .map(|p| display_path(p))
+            .collect();
+        Some(chain.join(" -> "))
+    }
+
+    pub fn enter(&mut self, path: PathBuf) {
+        self.loading.push(path);
+    }
+
+    pub fn leave(&mut self) {
+        self.loading.pop();
+    }
+
+    pub fn insert(&mut self, path: PathBuf, namespace: Rc<Namespace>) {
+        self.cache.insert(path, namespace);
+    }
+}
+
+pub fn display_path(path: &Path) -> String {
+    let relative = env::current_dir().ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf));
+
+    relative.unwrap_or_else(|| path.to_path_buf()).display().to_string()
+}
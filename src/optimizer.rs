@@ -0,0 +1,171 @@
+use crate::{evaluator::Evaluator, expr::{Expr, ExprKind, Literal}, stmt::{Stmt, StmtKind}, token::TokenType};
+
+/// Folds constant expressions and drops `if` and `while` branches whose
+/// condition is a constant that rules them out.
+///
+/// Folding runs the operator through the evaluator itself, so a folded value
+/// is exactly what the program would have computed, and an operation that
+/// would fail is left in place to fail at run time. Nodes that survive keep
+/// their ids, so resolver depths stay valid.
+pub fn optimize(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut optimizer = Optimizer { evaluator: Evaluator::new() };
+    optimizer.statements(stmts)
+}
+
+struct Optimizer {
+    evaluator: Evaluator,
+}
+
+impl Optimizer {
+    fn statements(&mut self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+        stmts.into_iter().filter_map(|stmt| self.statement(stmt)).collect()
+    }
+
+    // A statement in a position that can't be left empty, like a loop body.
+    fn nested(&mut self, stmt: Stmt) -> Stmt {
+        let span = stmt.span;
+        self.statement(stmt).unwrap_or_else(|| Stmt::new(span, StmtKind::Block(Box::default())))
+    }
+
+    // `None` when the statement can never do anything.
+    fn statement(&mut self, mut stmt: Stmt) -> Option<Stmt> {
+        stmt.kind = match stmt.kind {
+            StmtKind::If(mut condition, then_branch, else_branch) => {
+                self.expression(&mut condition);
+                if let Some(value) = constant(&condition) {
+                    return match self.evaluator.is_truthy(value) {
+                        true => self.statement(*then_branch),
+                        false => else_branch.and_then(|else_branch| self.statement(*else_branch)),
+                    };
+                }
+                let else_branch = else_branch.map(|else_branch| Box::new(self.nested(*else_branch)));
+                StmtKind::If(condition, Box::new(self.nested(*then_branch)), else_branch)
+            }
+            StmtKind::While(mut condition, body) => {
+                self.expression(&mut condition);
+                if constant(&condition).is_some_and(|value| !self.evaluator.is_truthy(value)) {
+                    return None;
+                }
+                StmtKind::While(condition, Box::new(self.nested(*body)))
+            }
+            StmtKind::DoWhile(body, mut condition) => {
+                self.expression(&mut condition);
+                StmtKind::DoWhile(Box::new(self.nested(*body)), condition)
+            }
+            StmtKind::ForIn(name, mut iterable, body) => {
+                self.expression(&mut iterable);
+                StmtKind::ForIn(name, iterable, Box::new(self.nested(*body)))
+            }
+            StmtKind::Block(stmts) => StmtKind::Block(Box::new(self.statements(*stmts))),
+            StmtKind::Function(mut fun_stmt) => {
+                for param in fun_stmt.params.iter_mut() {
+                    if let Some(default) = &mut param.default {
+                        self.expression(default);
+                    }
+                }
+                fun_stmt.body = self.statements(std::mem::take(&mut fun_stmt.body));
+                StmtKind::Function(fun_stmt)
+            }
+            StmtKind::Match(mut match_stmt) => {
+                self.expression(&mut match_stmt.subject);
+                match_stmt.arms = std::mem::take(&mut match_stmt.arms).into_iter()
+                    .map(|mut arm| {
+                        if let Some(guard) = &mut arm.guard {
+                            self.expression(guard);
+                        }
+                        arm.body = self.nested(arm.body);
+                        arm
+                    })
+                    .collect();
+                StmtKind::Match(match_stmt)
+            }
+            mut kind => {
+                match &mut kind {
+                    StmtKind::ExprStmt(expr) | StmtKind::PrintStmt(expr) => self.expression(expr),
+                    StmtKind::Declaration { initializer: Some(initializer), .. } => self.expression(initializer),
+                    StmtKind::Destructure { initializer, .. } => self.expression(initializer),
+                    StmtKind::Return(_, Some(value)) => self.expression(value),
+                    _ => {}
+                }
+                kind
+            }
+        };
+        Some(stmt)
+    }
+
+    fn expression(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::Lit(_) | ExprKind::Variable(_) => {}
+            ExprKind::Grouping(inner) | ExprKind::Unary(_, inner) => self.expression(inner),
+            ExprKind::Binary(left, _, right) | ExprKind::Logical(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExprKind::Assign(_, value) | ExprKind::Destructure(_, value) => self.expression(value),
+            ExprKind::Call(callee, _, arguments, keywords) => {
+                self.expression(callee);
+                for argument in arguments.iter_mut() {
+                    self.expression(argument);
+                }
+                for keyword in keywords.iter_mut() {
+                    self.expression(&mut keyword.value);
+                }
+            }
+            ExprKind::Get(object, _) => self.expression(object),
+            ExprKind::Index(object, _, index) => {
+                self.expression(object);
+                self.expression(index);
+            }
+            ExprKind::List(elements) => {
+                for element in elements.iter_mut() {
+                    self.expression(element);
+                }
+            }
+            ExprKind::Object(properties) => {
+                for property in properties.iter_mut() {
+                    self.expression(&mut property.value);
+                }
+            }
+        }
+
+        if let Some(folded) = self.fold(expr) {
+            *expr = folded;
+        }
+    }
+
+    // What `expr` can be replaced with now that its operands are folded.
+    fn fold(&mut self, expr: &Expr) -> Option<Expr> {
+        let value = match &expr.kind {
+            ExprKind::Grouping(inner) => constant(inner)?.clone(),
+            ExprKind::Unary(_, operand) => {
+                constant(operand)?;
+                self.evaluator.evaluate(expr).ok().map(|value| *value)?
+            }
+            ExprKind::Binary(left, _, right) => {
+                constant(left)?;
+                constant(right)?;
+                self.evaluator.evaluate(expr).ok().map(|value| *value)?
+            }
+            // The right operand only runs when the left doesn't already decide the result.
+            ExprKind::Logical(left, operator, right) => {
+                let truthy = self.evaluator.is_truthy(constant(left)?);
+                if truthy == (operator.token_type == TokenType::Or) {
+                    constant(left)?.clone()
+                }
+                else {
+                    return Some((**right).clone());
+                }
+            }
+            _ => return None,
+        };
+        Some(Expr { id: expr.id, span: expr.span, kind: ExprKind::Lit(value) })
+    }
+}
+
+// The value of an expression that is a plain number, string, boolean or nil.
+fn constant(expr: &Expr) -> Option<&Literal> {
+    match &expr.kind {
+        ExprKind::Lit(value @ (Literal::Number(_) | Literal::Str(_) | Literal::Boolean(_) | Literal::Nil)) => Some(value),
+        _ => None,
+    }
+}
@@ -1,99 +1,180 @@
-use crate::{expr::{Expr, Literal}, stmt::{FunctionStmt, Stmt}, token::{Token, TokenType}};
+use std::collections::HashMap;
+
+use crate::{expr::{Expr, ExprKind, KeywordArg, Literal, Pattern, Property}, stmt::{FunctionStmt, ImportStmt, MatchArm, MatchPattern, MatchStmt, Param, Stmt, StmtKind}, token::{Span, Token, TokenType}};
 
 pub struct Parser <'a> {
     tokens: &'a Vec<Token>,
-    current: usize
+    current: usize,
+    constants: Constants,
+    pub warnings: Vec<String>,
 }
 
 impl <'a> Parser <'a> {
     pub fn new(tokens: &'a Vec<Token>) -> Self {
         Self { 
             tokens,
-            current: 0
+            current: 0,
+            constants: Constants::new(),
+            warnings: Vec::new(),
         }
     }
 
-    fn expression(&mut self) -> Result<Expr, String> {
-        self.assignment()
+    fn begin_scope(&mut self) {
+        self.constants.begin_scope();
     }
 
-    fn equality(&mut self) -> Result<Expr, String> {
-        let mut expr = self.comparison()?;
+    fn end_scope(&mut self) {
+        self.constants.end_scope();
+    }
 
-        while self.mat(&[TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator = Box::from(self.previous()?.clone());
-            let right = self.comparison()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
-        }
-        Ok(expr)
+    fn declare(&mut self, name: &Token, constant: bool) -> Result<(), String> {
+        self.constants.declare(name, constant)
     }
 
-    fn comparison(&mut self) -> Result<Expr, String> {
-        let mut expr = self.term()?;
-        while self.mat(&[TokenType::Less, TokenType::LessEqual, TokenType::Greater, TokenType::GreaterEqual]) {
-            let operator = Box::from(self.previous()?.clone());
-            let right = self.term()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+    // From `start` through the last token consumed.
+    fn span_from(&self, start: Span) -> Span {
+        match self.previous() {
+            Ok(last) => start.to(last.span()),
+            Err(_) => start,
         }
-        Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, String> {
-        let mut expr = self.factor()?;
-        while self.mat(&[TokenType::Minus, TokenType::Plus]) {
-            let operator = Box::from(self.previous()?.clone());
-            let right = self.factor()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+    fn expression(&mut self) -> Result<Expr, String> {
+        if self.check(&TokenType::LeftBracket) || self.check(&TokenType::LeftBrace) {
+            if let Some(destructure) = self.destructuring_assignment()? {
+                return Ok(destructure);
+            }
         }
-        Ok(expr)
+
+        self.parse_precedence(Precedence::Assignment)
     }
 
-    fn factor(&mut self) -> Result<Expr, String> {
-        let mut expr = self.unary()?;
-        while self.mat(&[TokenType::Star, TokenType::Slash]) {
-            let operator = Box::from(self.previous()?.clone());
-            let right = self.unary()?;
-            expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
+    /// Parses an expression whose operators all bind at least as tightly as `min`.
+    fn parse_precedence(&mut self, min: Precedence) -> Result<Expr, String> {
+        let token = self.peek().clone();
+        let start = rule(&token.token_type);
+        let Some(prefix) = start.prefix else {
+            if start.infix.is_some() {
+                return Err(format!("[line {}] Error at '{}': Expect left operand before '{}'.", token.line, token.lexeme, token.lexeme));
+            }
+            return Err(format!("[line {}] Error at '{}': Expect expression.", token.line, token.lexeme));
+        };
+        self.advance()?;
+        let mut expr = self.parse_prefix(prefix, token)?;
+
+        while let Some((infix, precedence, associativity)) = rule(&self.peek().token_type).infix {
+            if precedence < min {
+                break;
+            }
+            let operator = self.advance()?.clone();
+            let right_min = match associativity {
+                Associativity::Left => precedence.next(),
+                Associativity::Right => precedence,
+            };
+            expr = self.parse_infix(infix, expr, operator, right_min)?;
         }
+
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, String> {
-        if self.mat(&[TokenType::Minus, TokenType::Bang]) {
-            let operator = Box::from(self.previous()?.clone());
-            let right = self.unary()?;
-            return Ok(Expr::Unary(operator, Box::new(right)));
+    // Like `parse_precedence`, but names the operator when its operand is missing.
+    fn operand(&mut self, min: Precedence, operator: &Token) -> Result<Expr, String> {
+        let token = self.peek();
+        if rule(&token.token_type).prefix.is_none() {
+            return Err(format!("[line {}] Error at '{}': Expect expression after '{}'.", token.line, token.lexeme, operator.lexeme));
         }
-
-        self.call()
+        self.parse_precedence(min)
     }
 
-    fn call(&mut self) -> Result<Expr, String> {
-        let mut expr = self.primary()?;
-
-        while true {
-            if self.mat(&[TokenType::LeftParen]) {
-                expr = self.finish_call(expr)?;
+    fn parse_prefix(&mut self, prefix: Prefix, token: Token) -> Result<Expr, String> {
+        let start = token.span();
+        match prefix {
+            Prefix::Literal => {
+                let literal = match (&token.token_type, token.literal.as_deref()) {
+                    (TokenType::Number(_), Some(Literal::Number(n))) => Literal::Number(*n),
+                    (TokenType::String(_), Some(Literal::Str(s))) => Literal::Str(s.clone()),
+                    (TokenType::True, _) => Literal::Boolean(true),
+                    (TokenType::False, _) => Literal::Boolean(false),
+                    _ => Literal::Nil,
+                };
+                Ok(Expr::new(start, ExprKind::Lit(literal)))
             }
-            else {
-                break;
+            Prefix::Variable => Ok(Expr::new(start, ExprKind::Variable(Box::from(token)))),
+            Prefix::Grouping => {
+                let expr = self.expression()?;
+                self.consume(&TokenType::RightParen, "Expect ')' after expression.".to_string())?;
+                Ok(Expr::new(self.span_from(start), ExprKind::Grouping(Box::from(expr))))
+            }
+            Prefix::List => self.list(start),
+            Prefix::Object => self.object(start),
+            Prefix::Unary => {
+                let right = self.operand(Precedence::Unary, &token)?;
+                Ok(Expr::new(self.span_from(start), ExprKind::Unary(Box::from(token), Box::new(right))))
             }
         }
+    }
 
-        Ok(expr)
+    fn parse_infix(&mut self, infix: Infix, left: Expr, operator: Token, right_min: Precedence) -> Result<Expr, String> {
+        let start = left.span;
+        match infix {
+            Infix::Binary => {
+                let right = self.operand(right_min, &operator)?;
+                Ok(Expr::new(self.span_from(start), ExprKind::Binary(Box::new(left), Box::from(operator), Box::new(right))))
+            }
+            Infix::Logical => {
+                let right = self.operand(right_min, &operator)?;
+                Ok(Expr::new(self.span_from(start), ExprKind::Logical(Box::from(left), Box::from(operator), Box::from(right))))
+            }
+            Infix::Assign => {
+                let value = self.operand(right_min, &operator)?;
+                match left.kind {
+                    ExprKind::Variable(var_name) => {
+                        self.constants.assign(&var_name)?;
+                        Ok(Expr::new(self.span_from(start), ExprKind::Assign(var_name, Box::from(value))))
+                    }
+                    _ => Err(format!("[line {}] Error at '=': Invalid assignment target.", operator.line)),
+                }
+            }
+            Infix::Call => self.finish_call(left),
+            Infix::Index => {
+                let index = self.expression()?;
+                let bracket = self.consume(&TokenType::RightBracket, "Expect ']' after index.".to_string())?.clone();
+                Ok(Expr::new(self.span_from(start), ExprKind::Index(Box::from(left), Box::from(bracket), Box::from(index))))
+            }
+            Infix::Get => {
+                let name = self.consume(&TokenType::Identifier, "Expect property name after '.'.".to_string())?.clone();
+                Ok(Expr::new(self.span_from(start), ExprKind::Get(Box::from(left), Box::from(name))))
+            }
+        }
     }
 
     fn finish_call(&mut self, expr: Expr) -> Result<Expr, String> {
 
         let mut args = Vec::new();
+        let mut keywords: Vec<KeywordArg> = Vec::new();
 
         if !self.check(&TokenType::RightParen) {
             loop {
-                if args.len() >= 255 {
-                    return Err("Can't have more than 255 arguments.".to_string());
+                if args.len() + keywords.len() >= 255 {
+                    let token = self.peek();
+                    return Err(format!("[line {}] Error at '{}': Can't have more than 255 arguments.", token.line, token.lexeme));
                 }
 
-                args.push(self.expression()?);
+                if self.check(&TokenType::Identifier) && self.check_next(&TokenType::Colon) {
+                    let name = self.advance()?.clone();
+                    self.advance()?;
+                    if keywords.iter().any(|keyword| keyword.name.lexeme == name.lexeme) {
+                        return Err(format!("[line {}] Error at '{}': Duplicate keyword argument '{}'.", name.line, name.lexeme, name.lexeme));
+                    }
+                    let value = self.expression()?;
+                    keywords.push(KeywordArg::new(name, value));
+                }
+                else if let Some(keyword) = keywords.last() {
+                    return Err(format!("[line {}] Error at '{}': Positional argument can't follow keyword arguments.", keyword.name.line, self.peek().lexeme));
+                }
+                else {
+                    args.push(self.expression()?);
+                }
 
                 if !self.mat(&[TokenType::Comma]) {
                     break;
@@ -101,48 +182,46 @@ impl <'a> Parser <'a> {
             }
         }
 
-        let paren = self.consume(&TokenType::RightParen, "Expect ')' after arguments.".to_string())?;
+        let paren = self.consume(&TokenType::RightParen, "Expect ')' after arguments.".to_string())?.clone();
 
-        Ok(Expr::Call(Box::from(expr), Box::from(paren.clone()), Box::from(args)))
+        Ok(Expr::new(self.span_from(expr.span), ExprKind::Call(Box::from(expr), Box::from(paren), Box::from(args), Box::from(keywords))))
     }
 
-    fn primary(&mut self) -> Result<Expr, String> {
-        if self.mat(&[TokenType::False]) {
-            return Ok(Expr::Lit(Literal::Boolean(false)));
-        }
-        else if self.mat(&[TokenType::True]) {
-            return Ok(Expr::Lit(Literal::Boolean(true)));
-        }
-        else if self.mat(&[TokenType::Nil]) {
-            return Ok(Expr::Lit(Literal::Nil));
-        }
-        else if self.mat(&[TokenType::LeftParen]) {
-            let expr = self.expression()?;
-            self.consume(&TokenType::RightParen, "Expect ')' after expression.".to_string())?;
-            return Ok(Expr::Grouping(Box::from(expr)));
-        }
+    fn list(&mut self, start: Span) -> Result<Expr, String> {
+        let mut elements = Vec::new();
+        if !self.check(&TokenType::RightBracket) {
+            loop {
+                elements.push(self.expression()?);
 
-        let token_type = &self.peek().token_type;
-        if let TokenType::Number(n) = token_type {
-            if self.mat(&[TokenType::Number(*n)]) {
-                if let Some(Literal::Number(num_val)) = self.previous()?.literal.as_deref() {
-                    return Ok(Expr::Lit(Literal::Number(*num_val)));
+                if !self.mat(&[TokenType::Comma]) {
+                    break;
                 }
             }
         }
-        else if let TokenType::String(s) = token_type {
-            if self.mat(&[TokenType::String(s.to_string())]) {
-                if let Some(Literal::Str(str_val)) = &self.previous()?.literal.as_deref() {
-                    return Ok(Expr::Lit(Literal::Str(String::from(str_val))));
+        self.consume(&TokenType::RightBracket, "Expect ']' after list elements.".to_string())?;
+        Ok(Expr::new(self.span_from(start), ExprKind::List(Box::from(elements))))
+    }
+
+    fn object(&mut self, start: Span) -> Result<Expr, String> {
+        let mut properties = Vec::new();
+        if !self.check(&TokenType::RightBrace) {
+            loop {
+                let name = self.consume(&TokenType::Identifier, "Expect property name.".to_string())?.clone();
+                let value = if self.mat(&[TokenType::Colon]) {
+                    self.expression()?
+                }
+                else {
+                    Expr::new(name.span(), ExprKind::Variable(Box::from(name.clone())))
+                };
+                properties.push(Property::new(name, value));
+
+                if !self.mat(&[TokenType::Comma]) {
+                    break;
                 }
             }
         }
-        else if self.mat(&[TokenType::Identifier]) {
-            return Ok(Expr::Variable(Box::from(self.previous()?.clone())));
-        }
-
-        let a = self.peek();
-        Err(format!("[line {}] Error at '{}': Expect expression.", a.line, a.lexeme))
+        self.consume(&TokenType::RightBrace, "Expect '}' after object properties.".to_string())?;
+        Ok(Expr::new(self.span_from(start), ExprKind::Object(Box::from(properties))))
     }
 
 
@@ -153,6 +232,13 @@ impl <'a> Parser <'a> {
         &self.peek().token_type == token_type
     }
 
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => &token.token_type == token_type,
+            None => false,
+        }
+    }
+
     fn advance(&mut self) -> Result<&Token, String> {
         if !self.is_at_end() {
             self.current += 1;
@@ -178,35 +264,46 @@ impl <'a> Parser <'a> {
     }
 
     fn is_at_end(&self) -> bool {
-        return self.peek().token_type == TokenType::EOF;
+        self.peek().token_type == TokenType::Eof
+    }
+
+    fn mat_contextual(&mut self, word: &str) -> bool {
+        if self.check(&TokenType::Identifier) && self.peek().lexeme == word {
+            return self.advance().is_ok();
+        }
+        false
     }
 
     fn mat(&mut self, v: &[TokenType]) -> bool {
         for token_type in v {
-            if self.check(&token_type) {
-                if let Ok(_) = self.advance() {
-                    return true;
-                }
-                else {
-                    return false;
-                }
+            if self.check(token_type) {
+                return self.advance().is_ok();
             }
         }
         false
     }
     
     pub fn parse(&mut self) -> Result<Expr, String> {
-        self.expression()
+        let expr = self.expression()?;
+        if !self.is_at_end() {
+            let token = self.peek();
+            return Err(format!("[line {}] Error at '{}': Expect end of expression.", token.line, token.lexeme));
+        }
+        Ok(expr)
     }
 
     pub fn _parse(&mut self) -> Result<Vec<Stmt>, String> {
         let mut stmts: Vec<Stmt> = Vec::new();
 
         while !self.is_at_end() { 
+            if self.mat(&[TokenType::Import]) {
+                stmts.push(self.import_declaration()?);
+                continue;
+            }
             stmts.push(self.declaration()?);
         }
 
-        return Ok(stmts)
+        Ok(stmts)
     }
 
     fn declaration(&mut self) -> Result<Stmt, String> {
@@ -214,10 +311,16 @@ impl <'a> Parser <'a> {
             return self.function("fun".to_string());
         }
         if self.mat(&[TokenType::Var]) {
-            return self.var_declaration();
+            return self.var_declaration(false);
         }
-        if self.mat(&[TokenType::LeftBrace]) {
-            return Ok(Stmt::Block(Box::from(self.block()?)));
+        if self.mat(&[TokenType::Const]) {
+            return self.var_declaration(true);
+        }
+        if self.mat_block() {
+            return self.block_statement();
+        }
+        if self.check(&TokenType::Import) {
+            return Err(format!("[line {}] Error at 'import': Import must be at top level.", self.peek().line));
         }
 
         self.statement().or_else(|err| {
@@ -226,20 +329,89 @@ impl <'a> Parser <'a> {
         })
     }
 
+    fn import_declaration(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous()?.clone();
+        let mut names = Vec::new();
+
+        if self.mat(&[TokenType::LeftBrace]) {
+            loop {
+                names.push(self.consume(&TokenType::Identifier, "Expect name to import.".to_string())?.clone());
+
+                if !self.mat(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.consume(&TokenType::RightBrace, "Expect '}' after imported names.".to_string())?;
+
+            if !self.mat_contextual("from") {
+                let token = self.peek();
+                return Err(format!("[line {}] Error at '{}': Expect 'from' after imported names.", token.line, token.lexeme));
+            }
+        }
+
+        let token = self.peek();
+        let path = match (&token.token_type, token.literal.as_deref()) {
+            (TokenType::String(_), Some(Literal::Str(path))) => path.clone(),
+            _ => return Err(format!("[line {}] Error at '{}': Expect module path.", token.line, token.lexeme)),
+        };
+        self.advance()?;
+
+        let mut alias = None;
+        if names.is_empty() {
+            if !self.mat_contextual("as") {
+                let token = self.peek();
+                return Err(format!("[line {}] Error at '{}': Expect 'as' after module path.", token.line, token.lexeme));
+            }
+            alias = Some(self.consume(&TokenType::Identifier, "Expect namespace name after 'as'.".to_string())?.clone());
+        }
+
+        self.consume(&TokenType::SemiColon, "Expect ';' after import.".to_string())?;
+
+        for name in alias.iter().chain(names.iter()) {
+            self.declare(name, false)?;
+        }
+
+        Ok(Stmt::new(self.span_from(keyword.span()), StmtKind::Import(Box::from(ImportStmt::new(keyword, path, alias, names)))))
+    }
+
     fn function(&mut self, kind: String) -> Result<Stmt, String> {
+        let start = self.previous()?.span();
         let name = self.consume(&TokenType::Identifier, format!("Expect {kind} name."))?.clone();
+        self.declare(&name, false)?;
         self.consume(&TokenType::LeftParen, format!("Expect '(' after {kind} name."))?;
-        let mut parameters = Vec::new();
+
+        self.begin_scope();
+        let signature = self.parameters();
+        let body = signature.and_then(|parameters| {
+            self.consume(&TokenType::LeftBrace, format!("Expect '{{' before {kind} body."))?;
+            Ok((parameters, self.block()?))
+        });
+        self.end_scope();
+        let (parameters, body) = body?;
+        let fun_stmt = FunctionStmt::new(name, parameters, body);
+
+        Ok(Stmt::new(self.span_from(start), StmtKind::Function(Box::from(fun_stmt))))
+    }
+
+    fn parameters(&mut self) -> Result<Vec<Param>, String> {
+        let mut parameters: Vec<Param> = Vec::new();
 
         if !self.check(&TokenType::RightParen) {
 
             loop {
-                if parameters.len() >= 255 {
-                    return Err("Can't have more than 255 parameters.".to_string());
+                let constant = self.mat(&[TokenType::Const]);
+                let rest = self.mat(&[TokenType::DotDotDot]);
+                let token = self.consume(&TokenType::Identifier, "Expect parameter name.".to_string())?.clone();
+
+                let mut default = None;
+                if self.mat(&[TokenType::Equal]) {
+                    default = Some(Box::from(self.expression()?));
                 }
 
-                let token = self.consume(&TokenType::Identifier, "Expect parameter name.".to_string())?.clone();
-                parameters.push(token);
+                let param = Param::new(token, constant, default, rest);
+                check_parameter(&parameters, &param)?;
+                self.declare(&param.name, constant)?;
+                parameters.push(param);
 
                 if !self.mat(&[TokenType::Comma]) {
                     break;
@@ -248,24 +420,131 @@ impl <'a> Parser <'a> {
         }
         self.consume(&TokenType::RightParen, "Expect ')' after parameters.".to_string())?;
 
-        self.consume(&TokenType::LeftBrace, format!("Expect '{{' before {kind} body."))?;
-        let body = self.block()?;
-        let fun_stmt = FunctionStmt::new(name, parameters, body);
-
-        Ok(Stmt::Function(Box::from(fun_stmt)))
+        Ok(parameters)
     }
 
-    fn var_declaration(&mut self) -> Result<Stmt, String> {
-        let name = self.consume(&TokenType::Identifier, "Expect variable name.".to_string())?.lexeme.clone();
+    fn var_declaration(&mut self, constant: bool) -> Result<Stmt, String> {
+        let start = self.previous()?.span();
+        if self.check(&TokenType::LeftBracket) || self.check(&TokenType::LeftBrace) {
+            return self.destructuring_declaration(start, constant);
+        }
+
+        let name = self.consume(&TokenType::Identifier, "Expect variable name.".to_string())?.clone();
         let mut initializer: Option<Box<Expr>> = None;
         if self.mat(&[TokenType::Equal]) {
             initializer = Some(Box::from(self.expression()?));
         }
+        else if constant {
+            return Err(format!("[line {}] Error at '{}': Constant '{}' must be initialized.", name.line, name.lexeme, name.lexeme));
+        }
         self.consume(&TokenType::SemiColon, "Expect ';' after variable declaration.".to_string())?;
+        self.declare(&name, constant)?;
         
-        return Ok(Stmt::Declaration { 
-            id: name.to_string(), initializer: initializer, 
-        });
+        Ok(Stmt::new(self.span_from(start), StmtKind::Declaration { 
+            name, initializer, constant,
+        }))
+    }
+
+    fn destructuring_declaration(&mut self, start: Span, constant: bool) -> Result<Stmt, String> {
+        let pattern = self.pattern()?;
+
+        check_pattern(&pattern)?;
+        self.consume(&TokenType::Equal, "Expect '=' after destructuring pattern.".to_string())?;
+        let initializer = self.expression()?;
+        self.consume(&TokenType::SemiColon, "Expect ';' after variable declaration.".to_string())?;
+
+        for name in pattern.names() {
+            self.declare(name, constant)?;
+        }
+
+        Ok(Stmt::new(self.span_from(start), StmtKind::Destructure { pattern: Box::from(pattern), initializer: Box::from(initializer), constant }))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, String> {
+        if self.mat(&[TokenType::LeftBracket]) {
+            let bracket = self.previous()?.clone();
+            let mut elements = Vec::new();
+            let mut rest = None;
+
+            if !self.check(&TokenType::RightBracket) {
+                loop {
+                    if self.mat(&[TokenType::DotDotDot]) {
+                        rest = Some(Box::from(self.consume(&TokenType::Identifier, "Expect name after '...'.".to_string())?.clone()));
+                        break;
+                    }
+                    elements.push(self.pattern()?);
+
+                    if !self.mat(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(&TokenType::RightBracket, "Expect ']' after list pattern.".to_string())?;
+
+            return Ok(Pattern::List(Box::from(bracket), elements, rest));
+        }
+
+        if self.mat(&[TokenType::LeftBrace]) {
+            let brace = self.previous()?.clone();
+            let mut fields = Vec::new();
+
+            if !self.check(&TokenType::RightBrace) {
+                loop {
+                    let key = self.consume(&TokenType::Identifier, "Expect property name in object pattern.".to_string())?.clone();
+                    let pattern = if self.mat(&[TokenType::Colon]) {
+                        self.pattern()?
+                    }
+                    else {
+                        Pattern::Name(Box::from(key.clone()))
+                    };
+                    fields.push((key, pattern));
+
+                    if !self.mat(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(&TokenType::RightBrace, "Expect '}' after object pattern.".to_string())?;
+
+            return Ok(Pattern::Object(Box::from(brace), fields));
+        }
+
+        let name = self.consume(&TokenType::Identifier, "Expect variable name in pattern.".to_string())?.clone();
+        Ok(Pattern::Name(Box::from(name)))
+    }
+
+    // Matches a '{' that opens a block. At the start of a statement it can
+    // also open an object pattern being assigned to, as in `{a, b} = p;`.
+    fn mat_block(&mut self) -> bool {
+        if !self.check(&TokenType::LeftBrace) {
+            return false;
+        }
+        let start = self.current;
+        let destructuring = self.pattern().is_ok() && self.check(&TokenType::Equal);
+        self.current = start;
+        !destructuring && self.mat(&[TokenType::LeftBrace])
+    }
+
+    // A '[' or '{' can open either a literal or a destructuring target, and
+    // only the '=' after it tells them apart, so try the pattern and rewind.
+    fn destructuring_assignment(&mut self) -> Result<Option<Expr>, String> {
+        let start = self.current;
+        let start_span = self.peek().span();
+
+        let pattern = match self.pattern() {
+            Ok(pattern) if self.mat(&[TokenType::Equal]) => pattern,
+            _ => {
+                self.current = start;
+                return Ok(None);
+            }
+        };
+
+        for name in pattern.names() {
+            self.constants.assign(name)?;
+        }
+
+        let value = self.expression()?;
+        Ok(Some(Expr::new(self.span_from(start_span), ExprKind::Destructure(Box::from(pattern), Box::from(value)))))
     }
 
     fn statement(&mut self) -> Result<Stmt, String> {
@@ -275,19 +554,123 @@ impl <'a> Parser <'a> {
         if self.mat(&[TokenType::If]) {
             return self.if_statement();
         }
-        if self.mat(&[TokenType::LeftBrace]) {
-            return Ok(Stmt::Block(Box::from(self.block()?)))
+        if self.mat_block() {
+            return self.block_statement();
         }
         if self.mat(&[TokenType::While]) {
             return self.fn_while();
         }
+        if self.mat(&[TokenType::Do]) {
+            return self.do_while_statement();
+        }
         if self.mat(&[TokenType::For]) {
-            return self.for_statement()
+            self.begin_scope();
+            let stmt = self.for_statement();
+            self.end_scope();
+            return stmt;
         }
         if self.mat(&[TokenType::Return]) {
             return self.return_statement();
         }
-        return self.expression_statement();
+        if self.mat(&[TokenType::Match]) {
+            return self.match_statement();
+        }
+        self.expression_statement()
+    }
+
+    fn match_statement(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous()?.clone();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'match'.".to_string())?;
+        let subject = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after match value.".to_string())?;
+        self.consume(&TokenType::LeftBrace, "Expect '{' before match arms.".to_string())?;
+
+        let mut arms = Vec::new();
+        let mut exhausted = false;
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let start = self.peek().clone();
+            let pattern = self.match_pattern()?;
+
+            let mut guard = None;
+            if self.mat(&[TokenType::If]) {
+                guard = Some(self.expression()?);
+            }
+            self.consume(&TokenType::FatArrow, "Expect '=>' after match pattern.".to_string())?;
+
+            self.begin_scope();
+            if let MatchPattern::Binding(name) = &pattern {
+                self.declare(name, false)?;
+            }
+            let body = self.statement();
+            self.end_scope();
+
+            if exhausted {
+                self.warnings.push(format!("[line {}] Warning at '{}': Unreachable match arm.", start.line, start.lexeme));
+            }
+            exhausted |= guard.is_none() && pattern.is_irrefutable();
+
+            arms.push(MatchArm::new(pattern, guard, body?));
+            self.mat(&[TokenType::Comma]);
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after match arms.".to_string())?;
+
+        Ok(Stmt::new(self.span_from(keyword.span()), StmtKind::Match(Box::from(MatchStmt::new(keyword, subject, arms)))))
+    }
+
+    fn match_pattern(&mut self) -> Result<MatchPattern, String> {
+        let mut alternatives = vec![self.single_match_pattern()?];
+
+        while self.mat(&[TokenType::Pipe]) {
+            alternatives.push(self.single_match_pattern()?);
+        }
+
+        if alternatives.len() == 1 {
+            return Ok(alternatives.remove(0));
+        }
+        if let Some(MatchPattern::Binding(name)) = alternatives.iter().find(|alternative| matches!(alternative, MatchPattern::Binding(_))) {
+            return Err(format!("[line {}] Error at '{}': Can't bind a name inside alternative patterns.", name.line, name.lexeme));
+        }
+        Ok(MatchPattern::Alternatives(alternatives))
+    }
+
+    fn single_match_pattern(&mut self) -> Result<MatchPattern, String> {
+        if self.mat(&[TokenType::Identifier]) {
+            let name = self.previous()?.clone();
+            if name.lexeme == "_" {
+                return Ok(MatchPattern::Wildcard);
+            }
+            return Ok(MatchPattern::Binding(name));
+        }
+
+        let low = self.pattern_literal()?;
+        if !self.check(&TokenType::DotDot) && !self.check(&TokenType::DotDotEqual) {
+            return Ok(MatchPattern::Literal(low));
+        }
+
+        let inclusive = self.advance()?.token_type == TokenType::DotDotEqual;
+        let high = self.pattern_literal()?;
+        match (low, high) {
+            (Literal::Number(low), Literal::Number(high)) => Ok(MatchPattern::Range(low, high, inclusive)),
+            _ => {
+                let token = self.previous()?;
+                Err(format!("[line {}] Error at '{}': Range bounds must be numbers.", token.line, token.lexeme))
+            }
+        }
+    }
+
+    fn pattern_literal(&mut self) -> Result<Literal, String> {
+        let negative = self.mat(&[TokenType::Minus]);
+        let token = self.advance()?.clone();
+
+        match (&token.token_type, token.literal.as_deref()) {
+            (TokenType::Number(_), Some(Literal::Number(n))) => Ok(Literal::Number(if negative { -n } else { *n })),
+            (TokenType::String(_), Some(Literal::Str(s))) if !negative => Ok(Literal::Str(s.clone())),
+            (TokenType::True, _) if !negative => Ok(Literal::Boolean(true)),
+            (TokenType::False, _) if !negative => Ok(Literal::Boolean(false)),
+            (TokenType::Nil, _) if !negative => Ok(Literal::Nil),
+            _ => Err(format!("[line {}] Error at '{}': Expect match pattern.", token.line, token.lexeme)),
+        }
     }
 
     fn return_statement(&mut self) -> Result<Stmt, String> {
@@ -300,18 +683,26 @@ impl <'a> Parser <'a> {
 
         self.consume(&TokenType::SemiColon, "Expect ';' after return value.".to_string())?;
 
-        return Ok(Stmt::Return(tok, value))
+        Ok(Stmt::new(self.span_from(tok.span()), StmtKind::Return(tok, value)))
     }
 
     fn for_statement(&mut self) -> Result<Stmt, String> {
+        let start = self.previous()?.span();
         self.consume(&TokenType::LeftParen, "Expect '(' after 'for'.".to_string())?;
 
+        let name_offset = if self.check(&TokenType::Var) { 1 } else { 0 };
+        let is_for_in = self.tokens.get(self.current + name_offset).is_some_and(|t| t.token_type == TokenType::Identifier)
+            && self.tokens.get(self.current + name_offset + 1).is_some_and(|t| t.token_type == TokenType::Identifier && t.lexeme == "in");
+        if is_for_in {
+            return self.for_in_statement(start);
+        }
+
         let mut init: Option<Stmt> = None;
         if self.mat(&[TokenType::SemiColon]) {
 
         }
         else if self.mat(&[TokenType::Var]) {
-            init = Some(self.var_declaration()?);
+            init = Some(self.var_declaration(false)?);
         }
         else {
             init = Some(self.expression_statement()?);
@@ -332,43 +723,76 @@ impl <'a> Parser <'a> {
 
         let mut stmt = self.statement()?;
 
+        // The desugared nodes all cover the whole `for` statement.
+        let span = self.span_from(start);
         if let Some(inc_val) = increment {
-            let mut v = Vec::new();
-            v.push(stmt);
-            v.push(Stmt::ExprStmt(Box::from(inc_val)));
-            stmt = Stmt::Block(Box::from(v));
+            let increment = Stmt::new(inc_val.span, StmtKind::ExprStmt(Box::from(inc_val)));
+            stmt = Stmt::new(span, StmtKind::Block(Box::from(vec![stmt, increment])));
         }
 
-        if let Some(_) = condition {
-
-        }
-        else {
-            condition = Some(Expr::Lit(Literal::Boolean(true)));
+        if condition.is_none() {
+            condition = Some(Expr::new(start, ExprKind::Lit(Literal::Boolean(true))));
         }
-        stmt = Stmt::While(Box::from(condition.unwrap()), Box::from(stmt));
+        stmt = Stmt::new(span, StmtKind::While(Box::from(condition.unwrap()), Box::from(stmt)));
 
         if let Some(init_val) = init {
-            let mut temp = Vec::new();
-            temp.push(init_val);
-            temp.push(stmt);
-            stmt = Stmt::Block(Box::from(Vec::from(temp)))
+            stmt = Stmt::new(span, StmtKind::Block(Box::from(vec![init_val, stmt])))
         }
 
-        return Ok(stmt);
+        Ok(stmt)
+
+    }
+
+    fn for_in_statement(&mut self, start: Span) -> Result<Stmt, String> {
+        self.mat(&[TokenType::Var]);
+        let name = self.consume(&TokenType::Identifier, "Expect loop variable name.".to_string())?.clone();
+        self.advance()?;
+        let iterable = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after for-in clause.".to_string())?;
+
+        self.declare(&name, false)?;
+        let body = self.statement()?;
+
+        Ok(Stmt::new(self.span_from(start), StmtKind::ForIn(Box::from(name), Box::from(iterable), Box::from(body))))
+    }
+
+    fn do_while_statement(&mut self) -> Result<Stmt, String> {
+        let start = self.previous()?.span();
+        let body = Box::from(self.statement()?);
+        self.consume(&TokenType::While, "Expect 'while' after do body.".to_string())?;
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'while'".to_string())?;
+        let expr = Box::from(self.expression()?);
+        self.consume(&TokenType::RightParen, "Expect ')' after condition.".to_string())?;
+        self.consume(&TokenType::SemiColon, "Expect ';' after do-while condition.".to_string())?;
 
+        Ok(Stmt::new(self.span_from(start), StmtKind::DoWhile(body, expr)))
     }
 
     fn fn_while(&mut self) -> Result<Stmt, String> {
+        let start = self.previous()?.span();
         self.consume(&TokenType::LeftParen, "Expect '(' after 'while'".to_string())?;
         let expr = Box::from(self.expression()?);
         self.consume(&TokenType::RightParen, "Expect ')' after condition.".to_string())?;
 
         let statement = Box::from(self.statement()?);
 
-        Ok(Stmt::While(expr, statement))
+        Ok(Stmt::new(self.span_from(start), StmtKind::While(expr, statement)))
+    }
+
+    fn block_statement(&mut self) -> Result<Stmt, String> {
+        let start = self.previous()?.span();
+        let statements = self.block()?;
+        Ok(Stmt::new(self.span_from(start), StmtKind::Block(Box::from(statements))))
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, String> {
+        self.begin_scope();
+        let statements = self.block_statements();
+        self.end_scope();
+        statements
+    }
+
+    fn block_statements(&mut self) -> Result<Vec<Stmt>, String> {
         let mut statements = Vec::new();
 
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
@@ -380,12 +804,14 @@ impl <'a> Parser <'a> {
     }
 
     fn print_statement(&mut self) -> Result<Stmt, String> {
+        let start = self.previous()?.span();
         let expr = self.expression()?;
         self.consume(&TokenType::SemiColon, "Expect ';' after value.".to_string())?;
-        return Ok(Stmt::PrintStmt(Box::from(expr)));
+        Ok(Stmt::new(self.span_from(start), StmtKind::PrintStmt(Box::from(expr))))
     }
 
     fn if_statement(&mut self) -> Result<Stmt, String> {
+        let start = self.previous()?.span();
         self.consume(&TokenType::LeftParen, "Expected '(' before expression.".to_string())?;
         let expr = Box::from(self.expression()?);
         self.consume(&TokenType::RightParen, "Expected ')' after expression".to_string())?;
@@ -397,75 +823,205 @@ impl <'a> Parser <'a> {
             else_val = Some(Box::from(self.statement()?));
         }
 
-        Ok(Stmt::If(expr, if_stmt, else_val))
+        Ok(Stmt::new(self.span_from(start), StmtKind::If(expr, if_stmt, else_val)))
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, String> {
         let expr = self.expression()?;
         self.consume(&TokenType::SemiColon, "Expected ';' after expression.".to_string())?;
-        return Ok(Stmt::ExprStmt(Box::from(expr)));
+        Ok(Stmt::new(self.span_from(expr.span), StmtKind::ExprStmt(Box::from(expr))))
     }
     
-    fn assignment(&mut self) -> Result<Expr, String> {
-        let expr = self.f_or()?;
+    fn synchronize(&mut self) -> Result<(), String> {
+        self.advance()?;
+        while !self.is_at_end() {
 
-        if self.mat(&[TokenType::Equal]) {
-            let _ = Box::from(self.previous()?.clone());
-            let val = self.assignment()?;
-
-            match expr {
-                Expr::Variable(var_name) => {
-                    return Ok(Expr::Assign(var_name, Box::from(val)));
-                },
-                _ => return Err("Invalid assignment target.".to_string()),
+            if self.previous()?.token_type == TokenType::SemiColon {
+                return Ok(());
+            }
+            match self.peek().token_type {
+                TokenType::Class | TokenType::Fun | TokenType::Var | TokenType::Const | TokenType::For |
+                TokenType::If | TokenType::Match | TokenType::Do | TokenType::While | TokenType::Print | TokenType::Return => {
+                    return Ok(());
+                }
+                _ => {}
             }
+            self.advance()?;
+        
         }
-
-        Ok(expr)
+        Ok(())
     }
 
-    fn f_or(&mut self) -> Result<Expr, String> {
-        let mut expr = self.f_and()?;
+}
 
-        while self.mat(&[TokenType::Or]) {
-            let tok = self.previous()?.clone();
-            let right = self.f_and()?;
-            expr = Expr::Logical(Box::from(expr), Box::from(tok), Box::from(right));
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Precedence {
+    Assignment,
+    Or,
+    And,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Call,
+}
 
-        return Ok(expr);
+impl Precedence {
+    pub(crate) fn next(self) -> Precedence {
+        match self {
+            Precedence::Assignment => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary | Precedence::Call => Precedence::Call,
+        }
     }
+}
 
-    fn f_and(&mut self) -> Result<Expr, String> {
-        let mut expr = self.equality()?;
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Associativity {
+    Left,
+    Right,
+}
 
-        while self.mat(&[TokenType::And]) {
-            let tok = self.previous()?.clone();
-            let right = self.f_and()?;
-            expr = Expr::Logical(Box::from(expr), Box::from(tok), Box::from(right))
-        }
+// What a token builds when it starts an expression.
+#[derive(Debug, Clone, Copy)]
+enum Prefix {
+    Literal,
+    Variable,
+    Grouping,
+    List,
+    Object,
+    Unary,
+}
 
-        return Ok(expr);
+// What a token builds when it follows a complete left operand. Postfix
+// operators (calls, indexing, property access) are infix rules that don't
+// parse a right operand of their own.
+#[derive(Debug, Clone, Copy)]
+enum Infix {
+    Binary,
+    Logical,
+    Assign,
+    Call,
+    Index,
+    Get,
+}
+
+/// The names declared in each enclosing block, mapped to whether they are
+/// const, which decides what a declaration or an assignment may name.
+pub struct Constants {
+    scopes: Vec<HashMap<String, bool>>,
+}
+
+impl Constants {
+    pub fn new() -> Self {
+        Constants { scopes: vec![HashMap::new()] }
     }
 
-    fn synchronize(&mut self) -> Result<(), String> {
-        self.advance()?;
-        while !self.is_at_end() {
+    pub fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
 
-            if self.previous()?.token_type == TokenType::SemiColon {
-                return Ok(());
-            }
-            match self.peek().token_type {
-                TokenType::Class | TokenType::Fun | TokenType::Var | TokenType::For |
-                TokenType::If | TokenType::While | TokenType::Print | TokenType::Return => {
-                    return Ok(());
-                }
-                _ => {}
+    pub fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // A const can't be declared over in its own scope, any more than assigned to.
+    pub fn declare(&mut self, name: &Token, constant: bool) -> Result<(), String> {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.get(&name.lexeme) == Some(&true) {
+                return Err(format!("[line {}] Error at '{}': Cannot redeclare constant '{}'.", name.line, name.lexeme, name.lexeme));
             }
-            self.advance()?;
-        
+            scope.insert(name.lexeme.clone(), constant);
+        }
+        Ok(())
+    }
+
+    pub fn assign(&self, name: &Token) -> Result<(), String> {
+        let constant = self.scopes.iter().rev()
+            .find_map(|scope| scope.get(&name.lexeme))
+            .copied()
+            .unwrap_or(false);
+        if constant {
+            return Err(format!("[line {}] Error at '{}': Cannot assign to constant '{}'.", name.line, name.lexeme, name.lexeme));
         }
         Ok(())
     }
+}
 
-}
\ No newline at end of file
+/// Checks that `param` may follow `parameters` in a parameter list.
+pub fn check_parameter(parameters: &[Param], param: &Param) -> Result<(), String> {
+    let token = &param.name;
+    let message = if parameters.len() >= 255 {
+        "Can't have more than 255 parameters."
+    }
+    else if parameters.last().is_some_and(|last| last.rest) {
+        "Rest parameter must be the last parameter."
+    }
+    else if param.rest && param.default.is_some() {
+        "Rest parameter can't have a default value."
+    }
+    else if !param.rest && param.default.is_none() && parameters.iter().any(|param| param.default.is_some()) {
+        "Parameter without a default value can't follow one with a default."
+    }
+    else {
+        return Ok(());
+    };
+    Err(format!("[line {}] Error at '{}': {}", token.line, token.lexeme, message))
+}
+
+/// Checks that a destructuring pattern binds each name once.
+pub fn check_pattern(pattern: &Pattern) -> Result<(), String> {
+    let names = pattern.names();
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].iter().any(|seen| seen.lexeme == name.lexeme) {
+            return Err(format!("[line {}] Error at '{}': Duplicate name '{}' in pattern.", name.line, name.lexeme, name.lexeme));
+        }
+    }
+    Ok(())
+}
+
+struct ParseRule {
+    prefix: Option<Prefix>,
+    infix: Option<(Infix, Precedence, Associativity)>,
+}
+
+/// The expression grammar in one place. A new operator is a new row here,
+/// plus a parselet only if it builds a new kind of node.
+fn rule(token_type: &TokenType) -> ParseRule {
+    use Associativity::{Left, Right};
+
+    let (prefix, infix) = match token_type {
+        TokenType::Number(_) | TokenType::String(_) |
+        TokenType::True | TokenType::False | TokenType::Nil => (Some(Prefix::Literal), None),
+        TokenType::Identifier => (Some(Prefix::Variable), None),
+        TokenType::LeftParen => (Some(Prefix::Grouping), Some((Infix::Call, Precedence::Call, Left))),
+        TokenType::LeftBracket => (Some(Prefix::List), Some((Infix::Index, Precedence::Call, Left))),
+        TokenType::LeftBrace => (Some(Prefix::Object), None),
+        TokenType::Dot => (None, Some((Infix::Get, Precedence::Call, Left))),
+        TokenType::Bang => (Some(Prefix::Unary), None),
+        TokenType::Minus => (Some(Prefix::Unary), Some((Infix::Binary, Precedence::Term, Left))),
+        TokenType::Plus => (None, Some((Infix::Binary, Precedence::Term, Left))),
+        TokenType::Star | TokenType::Slash => (None, Some((Infix::Binary, Precedence::Factor, Left))),
+        TokenType::Less | TokenType::LessEqual |
+        TokenType::Greater | TokenType::GreaterEqual => (None, Some((Infix::Binary, Precedence::Comparison, Left))),
+        TokenType::EqualEqual | TokenType::BangEqual => (None, Some((Infix::Binary, Precedence::Equality, Left))),
+        TokenType::And => (None, Some((Infix::Logical, Precedence::And, Left))),
+        TokenType::Or => (None, Some((Infix::Logical, Precedence::Or, Left))),
+        TokenType::Equal => (None, Some((Infix::Assign, Precedence::Assignment, Right))),
+        _ => (None, None),
+    };
+
+    ParseRule { prefix, infix }
+}
+
+/// How tightly an infix operator binds, for code that has to print
+/// expressions the way this parser reads them back.
+pub(crate) fn infix_binding(token_type: &TokenType) -> Option<(Precedence, Associativity)> {
+    rule(token_type).infix.map(|(_, precedence, associativity)| (precedence, associativity))
+}
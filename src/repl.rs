@@ -0,0 +1,196 @@
+use std::{env, fs, path::{Path, PathBuf}};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use crate::{checker, evaluator::Evaluator, flow, optimizer, parser::Parser, scanner::Scanner};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+enum Flow {
+    Continue,
+    Quit,
+}
+
+/// An interactive session. One `Evaluator` lives for the whole session, so
+/// what earlier inputs define stays visible to later ones, and no error ends it.
+pub struct Repl {
+    evaluator: Evaluator,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl { evaluator: Evaluator::new() }
+    }
+
+    pub fn run(&mut self) {
+        let mut editor = match DefaultEditor::new() {
+            Ok(editor) => editor,
+            Err(e) => {
+                eprintln!("Can't start the REPL: {e}");
+                return;
+            }
+        };
+        let history = history_path();
+        if let Some(history) = &history {
+            let _ = editor.load_history(history);
+        }
+
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+            match editor.readline(prompt) {
+                Ok(line) => {
+                    buffer.push_str(&line);
+                    buffer.push('\n');
+                    if is_incomplete(&buffer) {
+                        continue;
+                    }
+
+                    let input = std::mem::take(&mut buffer);
+                    let input = input.trim();
+                    if input.is_empty() {
+                        continue;
+                    }
+                    let _ = editor.add_history_entry(input);
+                    if let Flow::Quit = self.handle(input) {
+                        break;
+                    }
+                }
+                // Ctrl-C throws away a half-typed input but keeps the session.
+                Err(ReadlineError::Interrupted) => buffer.clear(),
+                Err(ReadlineError::Eof) => break,
+                Err(e) => {
+                    eprintln!("{e}");
+                    break;
+                }
+            }
+        }
+
+        if let Some(history) = &history {
+            let _ = editor.save_history(history);
+        }
+    }
+
+    fn handle(&mut self, input: &str) -> Flow {
+        match input.strip_prefix(':') {
+            Some(command) => self.command(command),
+            None => {
+                self.eval(input, true);
+                Flow::Continue
+            }
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Flow {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+
+        match name {
+            "quit" | "q" => return Flow::Quit,
+            "env" => self.print_env(),
+            "load" if !argument.is_empty() => self.load(argument),
+            "load" => eprintln!("Usage: :load <file>"),
+            _ => eprintln!("Unknown command ':{}'. Try :load <file>, :env or :quit.", name),
+        }
+        Flow::Continue
+    }
+
+    fn load(&mut self, filename: &str) {
+        let source = match fs::read_to_string(filename) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Can't read '{}': {}", filename, e);
+                return;
+            }
+        };
+
+        // Imports in the file resolve next to it; the session's own resolve
+        // from the working directory again afterwards.
+        self.evaluator.set_script(Path::new(filename));
+        self.eval(&source, false);
+        self.evaluator.set_script(Path::new(""));
+    }
+
+    fn print_env(&self) {
+        for (name, value, constant) in self.evaluator.globals.borrow().bindings() {
+            let value = value.map(|value| value.to_string()).unwrap_or_else(|| "nil".to_string());
+            println!("{}{} = {}", if constant { "const " } else { "" }, name, value);
+        }
+    }
+
+    // Runs `source` as a program. With `echo`, something that isn't a program
+    // but is a single expression gets evaluated and its value printed.
+    fn eval(&mut self, source: &str, echo: bool) {
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens();
+        if !scanner.errors.is_empty() {
+            for e in &scanner.errors {
+                eprintln!("{e}");
+            }
+            return;
+        }
+
+        let mut parser = Parser::new(&scanner.tokens);
+        let error = match parser._parse() {
+            Ok(stmts) => {
+                for warning in &parser.warnings {
+                    eprintln!("{warning}");
+                }
+                if let Err(e) = self.evaluator.resolve(&stmts) {
+                    eprintln!("{e}");
+                    return;
+                }
+                let globals = self.evaluator.globals.borrow();
+                for warning in checker::check(&stmts, &globals).into_iter().chain(flow::analyze(&stmts, &globals)) {
+                    eprintln!("{warning}");
+                }
+                drop(globals);
+                if let Err(e) = self.evaluator.execute_all(&optimizer::optimize(stmts)) {
+                    eprintln!("{e}");
+                }
+                return;
+            }
+            Err(e) => e,
+        };
+
+        if echo {
+            if let Ok(expr) = Parser::new(&scanner.tokens).parse() {
+                match self.evaluator.evaluate(&expr) {
+                    Ok(value) => self.evaluator.writer(&value),
+                    Err(e) => eprintln!("{e}"),
+                }
+                return;
+            }
+        }
+        eprintln!("{error}");
+    }
+}
+
+// Whether `source` still has an open bracket or string, so the next line
+// belongs to the same input.
+fn is_incomplete(source: &str) -> bool {
+    let mut depth = 0;
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Skips to the closing quote, if there is one.
+            '"' if !chars.any(|c| c == '"') => return true,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.find(|&c| c == '\n');
+            }
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
+
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".lox_history"))
+}
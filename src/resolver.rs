@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use crate::{expr::{Expr, ExprKind, NodeId}, stmt::{FunctionStmt, MatchPattern, Stmt, StmtKind}, token::Token};
+
+/// Where a local variable lives: `depth` environments out from its use, in
+/// slot `slot` of that environment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Local {
+    pub depth: usize,
+    pub slot: usize,
+}
+
+/// The local each variable use refers to.
+///
+/// Uses that no enclosing local declares are missing; they refer to the top
+/// level of the file and are looked up by name when they run.
+#[derive(Default)]
+pub struct Locals {
+    // Indexed by node id, which are handed out densely; this is read on
+    // every variable access, so it skips hashing.
+    variables: Vec<Option<Local>>,
+    // A destructuring assignment writes several names; theirs follow `Pattern::names`.
+    patterns: HashMap<NodeId, Vec<Option<Local>>>,
+}
+
+impl Locals {
+    pub fn variable(&self, id: NodeId) -> Option<Local> {
+        self.variables.get(id.0).copied().flatten()
+    }
+
+    pub fn pattern(&self, id: NodeId, index: usize) -> Option<Local> {
+        self.patterns.get(&id).and_then(|locals| locals.get(index).copied().flatten())
+    }
+
+    fn insert(&mut self, id: NodeId, local: Local) {
+        if self.variables.len() <= id.0 {
+            self.variables.resize(id.0 + 1, None);
+        }
+        self.variables[id.0] = Some(local);
+    }
+
+    /// Adds what another resolver run found.
+    pub fn extend(&mut self, other: Locals) {
+        for (id, local) in other.variables.into_iter().enumerate() {
+            if let Some(local) = local {
+                self.insert(NodeId(id), local);
+            }
+        }
+        self.patterns.extend(other.patterns);
+    }
+}
+
+struct Variable {
+    slot: usize,
+    // Whether the initializer has finished.
+    defined: bool,
+}
+
+/// Works out `Locals` for a program before it runs.
+///
+/// Its scopes have to line up with the local environments the evaluator
+/// creates: one per block, one per call holding the parameters and the body,
+/// one per `for`-`in` iteration and one per match arm. Slots are numbered in
+/// the order the evaluator defines variables in each of them.
+pub struct Resolver {
+    scopes: Vec<HashMap<String, Variable>>,
+    functions: usize,
+    locals: Locals,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver {
+            scopes: Vec::new(),
+            functions: 0,
+            locals: Locals::default(),
+        }
+    }
+
+    pub fn resolve(mut self, stmts: &[Stmt]) -> Result<Locals, String> {
+        for stmt in stmts {
+            self.statement(stmt)?;
+        }
+        Ok(self.locals)
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) -> Result<(), String> {
+        let Some(scope) = self.scopes.last_mut() else {
+            return Ok(());
+        };
+        if scope.contains_key(&name.lexeme) {
+            return Err(format!("[line {}] Error at '{}': Already a variable with this name in this scope.", name.line, name.lexeme));
+        }
+        scope.insert(name.lexeme.clone(), Variable { slot: scope.len(), defined: false });
+        Ok(())
+    }
+
+    fn define(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        let slot = scope.len();
+        scope.entry(name.lexeme.clone())
+            .or_insert(Variable { slot, defined: false })
+            .defined = true;
+    }
+
+    fn local(&self, name: &Token) -> Option<Local> {
+        self.scopes.iter().rev().enumerate().find_map(|(depth, scope)| {
+            scope.get(&name.lexeme).map(|variable| Local { depth, slot: variable.slot })
+        })
+    }
+
+    fn record(&mut self, id: NodeId, name: &Token) {
+        if let Some(local) = self.local(name) {
+            self.locals.insert(id, local);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), String> {
+        match &stmt.kind {
+            StmtKind::ExprStmt(expr) | StmtKind::PrintStmt(expr) => self.expression(expr)?,
+            StmtKind::Declaration { name, initializer, .. } => {
+                self.declare(name)?;
+                if let Some(initializer) = initializer {
+                    self.expression(initializer)?;
+                }
+                self.define(name);
+            }
+            StmtKind::Destructure { pattern, initializer, .. } => {
+                for name in pattern.names() {
+                    self.declare(name)?;
+                }
+                self.expression(initializer)?;
+                for name in pattern.names() {
+                    self.define(name);
+                }
+            }
+            StmtKind::Block(stmts) => {
+                self.begin_scope();
+                let result = stmts.iter().try_for_each(|stmt| self.statement(stmt));
+                self.end_scope();
+                result?;
+            }
+            StmtKind::Function(fun_stmt) => {
+                // Defined straight away so the body can call itself.
+                self.declare(&fun_stmt.name)?;
+                self.define(&fun_stmt.name);
+                self.function(fun_stmt)?;
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.expression(condition)?;
+                self.statement(then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch)?;
+                }
+            }
+            StmtKind::While(condition, body) => {
+                self.expression(condition)?;
+                self.statement(body)?;
+            }
+            StmtKind::DoWhile(body, condition) => {
+                self.statement(body)?;
+                self.expression(condition)?;
+            }
+            StmtKind::ForIn(name, iterable, body) => {
+                self.expression(iterable)?;
+                self.begin_scope();
+                self.define(name);
+                let result = self.statement(body);
+                self.end_scope();
+                result?;
+            }
+            StmtKind::Return(keyword, value) => {
+                if self.functions == 0 {
+                    return Err(format!("[line {}] Error at '{}': Can't return from top-level code.", keyword.line, keyword.lexeme));
+                }
+                if let Some(value) = value {
+                    self.expression(value)?;
+                }
+            }
+            StmtKind::Import(import) => {
+                for name in import.alias.iter().chain(import.names.iter()) {
+                    self.declare(name)?;
+                    self.define(name);
+                }
+            }
+            StmtKind::Match(match_stmt) => {
+                self.expression(&match_stmt.subject)?;
+                for arm in &match_stmt.arms {
+                    self.begin_scope();
+                    if let MatchPattern::Binding(name) = &arm.pattern {
+                        self.define(name);
+                    }
+                    let result = self.arm(arm.guard.as_ref(), &arm.body);
+                    self.end_scope();
+                    result?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn arm(&mut self, guard: Option<&Expr>, body: &Stmt) -> Result<(), String> {
+        if let Some(guard) = guard {
+            self.expression(guard)?;
+        }
+        self.statement(body)
+    }
+
+    fn function(&mut self, fun_stmt: &FunctionStmt) -> Result<(), String> {
+        self.functions += 1;
+        self.begin_scope();
+        let result = self.function_scope(fun_stmt);
+        self.end_scope();
+        self.functions -= 1;
+        result
+    }
+
+    // Parameters and body share the call's environment.
+    fn function_scope(&mut self, fun_stmt: &FunctionStmt) -> Result<(), String> {
+        for param in &fun_stmt.params {
+            if let Some(default) = &param.default {
+                self.expression(default)?;
+            }
+            self.declare(&param.name)?;
+            self.define(&param.name);
+        }
+        fun_stmt.body.iter().try_for_each(|stmt| self.statement(stmt))
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Result<(), String> {
+        match &expr.kind {
+            ExprKind::Lit(_) => {}
+            ExprKind::Grouping(inner) | ExprKind::Unary(_, inner) => self.expression(inner)?,
+            ExprKind::Binary(left, _, right) | ExprKind::Logical(left, _, right) => {
+                self.expression(left)?;
+                self.expression(right)?;
+            }
+            ExprKind::Variable(name) => {
+                if self.scopes.last().and_then(|scope| scope.get(&name.lexeme)).is_some_and(|variable| !variable.defined) {
+                    return Err(format!("[line {}] Error at '{}': Can't read local variable in its own initializer.", name.line, name.lexeme));
+                }
+                self.record(expr.id, name);
+            }
+            ExprKind::Assign(name, value) => {
+                self.expression(value)?;
+                self.record(expr.id, name);
+            }
+            ExprKind::Destructure(pattern, value) => {
+                self.expression(value)?;
+                let locals = pattern.names().into_iter().map(|name| self.local(name)).collect();
+                self.locals.patterns.insert(expr.id, locals);
+            }
+            ExprKind::Call(callee, _, arguments, keywords) => {
+                self.expression(callee)?;
+                for argument in arguments.iter() {
+                    self.expression(argument)?;
+                }
+                for keyword in keywords.iter() {
+                    self.expression(&keyword.value)?;
+                }
+            }
+            ExprKind::Get(object, _) => self.expression(object)?,
+            ExprKind::Index(object, _, index) => {
+                self.expression(object)?;
+                self.expression(index)?;
+            }
+            ExprKind::List(elements) => {
+                for element in elements.iter() {
+                    self.expression(element)?;
+                }
+            }
+            ExprKind::Object(properties) => {
+                for property in properties.iter() {
+                    self.expression(&property.value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
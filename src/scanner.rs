@@ -1,4 +1,4 @@
-use crate::{expr::Literal, token::{Token, TokenType}};
+use crate::{error::LexError, expr::Literal, token::{Token, TokenType}};
 
 #[derive(Debug)]
 pub struct Scanner <'a> {
@@ -6,8 +6,11 @@ pub struct Scanner <'a> {
     start: usize,
     current: usize,
     line: usize,
+    // Where the current line starts, for token columns.
+    line_start: usize,
     pub tokens: Vec<Token>,
-    pub code:i32,
+    // What couldn't be made into tokens; scanning carries on past each one.
+    pub errors: Vec<LexError>,
 }
 
 impl <'a> Scanner <'a> {
@@ -17,8 +20,9 @@ impl <'a> Scanner <'a> {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
             tokens: Vec::new(),
-            code: 0,
+            errors: Vec::new(),
         }
     }
 
@@ -27,7 +31,7 @@ impl <'a> Scanner <'a> {
             self.start = self.current;
             self.scan_token_alternative();
         }
-        self.tokens.push(Token { token_type: TokenType::EOF, lexeme: "".to_string(), literal: None, line: 1 });
+        self.tokens.push(Token { token_type: TokenType::Eof, lexeme: "".to_string(), literal: None, line: 1, column: 0 });
 
         &self.tokens
     }
@@ -35,16 +39,31 @@ impl <'a> Scanner <'a> {
     fn scan_token_alternative(&mut self) {
         let c = self.advance();
 
-        let _ = match c {
+        match c {
 
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
             '*' => self.add_token(TokenType::Star),
             ',' => self.add_token(TokenType::Comma),
+            ':' => self.add_token(TokenType::Colon),
             '+' => self.add_token(TokenType::Plus),
-            '.' => self.add_token(TokenType::Dot),
+            '.' => {
+                let token_type = if self.match_next_str("..") {
+                    TokenType::DotDotDot
+                } else if self.match_next_str(".=") {
+                    TokenType::DotDotEqual
+                } else if self.match_next('.') {
+                    TokenType::DotDot
+                } else {
+                    TokenType::Dot
+                };
+                self.add_token(token_type)
+            }
+            '|' => self.add_token(TokenType::Pipe),
             '-' => self.add_token(TokenType::Minus),
             ';' => self.add_token(TokenType::SemiColon),
             '!' => {
@@ -52,7 +71,13 @@ impl <'a> Scanner <'a> {
                 self.add_token(token_type)
             }
             '=' => {
-                let token_type = if !self.match_next('=') {TokenType::Equal} else {TokenType::EqualEqual};
+                let token_type = if self.match_next('=') {
+                    TokenType::EqualEqual
+                } else if self.match_next('>') {
+                    TokenType::FatArrow
+                } else {
+                    TokenType::Equal
+                };
                 self.add_token(token_type)
             }
             '<' => {
@@ -69,7 +94,10 @@ impl <'a> Scanner <'a> {
                     self.add_token(token_type)
                 }
             }
-            '\n' => self.line += 1,
+            '\n' => {
+                self.line += 1;
+                self.line_start = self.current;
+            }
             ' ' => (),
             '\r' => (),
             '\t' => (),
@@ -79,8 +107,7 @@ impl <'a> Scanner <'a> {
             '\0' => (),
 
             _ => {
-                eprintln!("[line {}] Error: Unexpected character: {}", self.line, c);
-                self.code = 65;
+                self.errors.push(LexError::new(self.line, format!("Unexpected character: {}", c)));
             }
         };
     }
@@ -90,13 +117,22 @@ impl <'a> Scanner <'a> {
             return false;
         }
         self.current += 1;
-        return true;
+        true
+    }
+
+    fn match_next_str(&mut self, expected: &str) -> bool {
+        let len = expected.chars().count();
+        if !self.source.chars().skip(self.current).take(len).eq(expected.chars()) {
+            return false;
+        }
+        self.current += len;
+        true
     }
 
     fn advance (&mut self) -> char {
-        let temp = self.current.clone();
+        let temp = self.current;
         self.current += 1;
-        self.source.chars().nth(temp).unwrap_or_else(|| '\0')
+        self.source.chars().nth(temp).unwrap_or('\0')
     }
 
     fn is_at_end(&self) -> bool {
@@ -104,7 +140,7 @@ impl <'a> Scanner <'a> {
     }
 
     fn peek(&self) -> char {
-        self.source.chars().nth(self.current).unwrap_or_else(|| '\0')
+        self.source.chars().nth(self.current).unwrap_or('\0')
     }
 
     fn slash(&mut self) -> Option<TokenType> {
@@ -124,17 +160,26 @@ impl <'a> Scanner <'a> {
     }
 
     fn add_token_helper(&mut self, token_type: TokenType, literal: Option<Box<Literal>>) {
+        let column = self.start - self.line_start + 1;
+        self.add_token_at(token_type, literal, self.line, column);
+    }
+
+    fn add_token_at(&mut self, token_type: TokenType, literal: Option<Box<Literal>>, line: usize, column: usize) {
         let text: &String = &self.source.chars().take(self.current).skip(self.start).collect();
-        self.tokens.push(Token {token_type, lexeme: String::from(text), literal, line:self.line});
+        self.tokens.push(Token {token_type, lexeme: String::from(text), literal, line, column});
     }
 
     fn make_string_alternative(&mut self) {
+        // A string can run over several lines, and its token sits where it starts.
+        let (line, column) = (self.line, self.start - self.line_start + 1);
         while !self.is_at_end() && self.peek() != '"' {
-            self.advance();
+            if self.advance() == '\n' {
+                self.line += 1;
+                self.line_start = self.current;
+            }
         }
         if self.is_at_end() {
-            self.code = 65;
-            eprintln!("[line {}] Error: Unterminated string.", self.line);
+            self.errors.push(LexError::new(self.line, "Unterminated string.".to_string()));
         } 
         else {
             self.advance();
@@ -142,12 +187,12 @@ impl <'a> Scanner <'a> {
             let text_literal: String = self.source.chars().take(self.current).skip(self.start).collect();
             let literal = text_literal.replace('"', "");
     
-            self.add_token_helper(TokenType::String(text_literal), Some(Box::new(Literal::Str(literal))));
+            self.add_token_at(TokenType::String(text_literal), Some(Box::new(Literal::Str(literal))), line, column);
         }
     }
     
     fn peek_next(&self) -> char {
-        return self.source.chars().nth(self.current+1).unwrap();
+        self.source.chars().nth(self.current+1).unwrap()
     }
 
     fn number_alternative(&mut self) {
@@ -155,13 +200,11 @@ impl <'a> Scanner <'a> {
             self.advance();
         }
 
-        if self.peek() == '.' {
-            if is_digit(self.peek_next()) {
-                self.advance();
+        if self.peek() == '.' && is_digit(self.peek_next()) {
+            self.advance();
 
-                while !self.is_at_end() && is_digit(self.peek()) {
-                    self.advance();
-                }
+            while !self.is_at_end() && is_digit(self.peek()) {
+                self.advance();
             }
         }
 
@@ -178,11 +221,15 @@ impl <'a> Scanner <'a> {
         let kind = match ident {
             "and" => TokenType::And,
             "class" => TokenType::Class,
+            "const" => TokenType::Const,
+            "do" => TokenType::Do,
             "else" => TokenType::Else,
             "false" => TokenType::False,
             "for" => TokenType::For,
             "fun" => TokenType::Fun,
             "if" => TokenType::If,
+            "import" => TokenType::Import,
+            "match" => TokenType::Match,
             "nil" => TokenType::Nil,
             "or" => TokenType::Or,
             "print" => TokenType::Print,
@@ -201,20 +248,15 @@ impl <'a> Scanner <'a> {
 }
 
 fn normalize_number_string(num_str: &str) -> f64 {
-    match num_str.parse::<f64>() {
-        Ok(num) => {
-            num
-        }
-        Err(_) => 0.0
-    }
+    num_str.parse::<f64>().unwrap_or(0.0)
 }
 
 fn is_digit(c: char) -> bool {
-    c >= '0' && c <= '9'
+    c.is_ascii_digit()
 }
 
 fn is_alpha(c: char) -> bool {
-    return c >= 'a' && c <= 'z' || c >= 'A' && c <= 'Z' || c == '_';
+    c.is_ascii_lowercase() || c.is_ascii_uppercase() || c == '_'
 }
 
 fn is_alpha_numric(c: char) -> bool {
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use crate::stmt::{FunctionStmt, Stmt, StmtKind};
+
+/// The names in reach at each point of a walk over a program that doesn't run
+/// it, following the scoping the resolver uses. Each name maps to whatever the
+/// walk wants to know about it, and `other` stands for a name that could hold
+/// anything.
+pub struct Scopes<B> {
+    scopes: Vec<HashMap<String, B>>,
+    // Top-level names are looked up when a call runs, so a body can call a
+    // function declared further down the file.
+    globals: HashMap<String, B>,
+    other: B,
+}
+
+impl<B: Copy> Scopes<B> {
+    pub fn new(other: B) -> Self {
+        Scopes { scopes: Vec::new(), globals: HashMap::new(), other }
+    }
+
+    /// Declares every top-level name of `stmts` up front, each `fun` bound to
+    /// what `function` makes of it and every other name to `other`.
+    pub fn top_level<'a>(&mut self, stmts: &'a [Stmt], mut function: impl FnMut(&'a Stmt, &'a FunctionStmt) -> B) {
+        for stmt in stmts {
+            let names = match &stmt.kind {
+                StmtKind::Function(fun_stmt) => {
+                    let binding = function(stmt, fun_stmt);
+                    self.global(&fun_stmt.name.lexeme, binding);
+                    continue;
+                }
+                StmtKind::Declaration { name, .. } => vec![name],
+                StmtKind::Destructure { pattern, .. } => pattern.names(),
+                StmtKind::Import(import) => import.alias.iter().chain(import.names.iter()).collect(),
+                _ => continue,
+            };
+            for name in names {
+                self.global(&name.lexeme, self.other);
+            }
+        }
+    }
+
+    // A name declared more than once at the top level could be either at run time.
+    pub fn global(&mut self, name: &str, binding: B) {
+        let binding = if self.globals.contains_key(name) { self.other } else { binding };
+        self.globals.insert(name.to_string(), binding);
+    }
+
+    pub fn declare(&mut self, name: &str, binding: B) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), binding);
+        }
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<B> {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+            .copied()
+    }
+
+    pub fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+}
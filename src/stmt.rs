@@ -1,41 +1,304 @@
-use crate::{evaluator::RuntimeException, expr::Expr, token::Token, visitor::{StmtAccept, StmtVisitor}};
+use std::fmt;
 
+use crate::{evaluator::RuntimeException, expr::{Expr, ExprKind, Literal, NodeId, Pattern}, token::{Span, Token}, visitor::{StmtAccept, StmtVisitor}};
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub id: NodeId,
+    pub span: Span,
+    pub kind: StmtKind,
+}
+
+impl Stmt {
+    pub fn new(span: Span, kind: StmtKind) -> Self {
+        Stmt { id: NodeId::fresh(), span, kind }
+    }
+}
+
+impl PartialEq for Stmt {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+#[allow(clippy::box_collection)]
 #[derive(Debug, Clone, PartialEq)]
-pub enum Stmt{
+pub enum StmtKind {
     ExprStmt(Box<Expr>),
     PrintStmt(Box<Expr>),
-    Declaration{id: String, initializer: Option<Box<Expr>>},
+    Declaration{name: Token, initializer: Option<Box<Expr>>, constant: bool},
+    Destructure{pattern: Box<Pattern>, initializer: Box<Expr>, constant: bool},
     Block(Box<Vec<Stmt>>),
     Function(Box<FunctionStmt>),
     If(Box<Expr>, Box<Stmt>, Option<Box<Stmt>>),
     While(Box<Expr>, Box<Stmt>),
+    DoWhile(Box<Stmt>, Box<Expr>),
+    ForIn(Box<Token>, Box<Expr>, Box<Stmt>),
     Return(Box<Token>, Option<Box<Expr>>),
+    Import(Box<ImportStmt>),
+    Match(Box<MatchStmt>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionStmt {
     pub name: Token,
-    pub params: Vec<Token>,
+    pub params: Vec<Param>,
     pub body: Vec<Stmt>,
 }
 
 impl FunctionStmt {
-    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>) -> Self {
+    pub fn new(name: Token, params: Vec<Param>, body: Vec<Stmt>) -> Self {
         FunctionStmt { name, params, body }
     }
+
+    /// How many arguments a call has to pass.
+    pub fn arity(&self) -> usize {
+        self.params.iter()
+            .filter(|param| param.default.is_none() && !param.rest)
+            .count()
+    }
+
+    // `None` when a rest parameter takes any number of trailing arguments.
+    pub fn max_arity(&self) -> Option<usize> {
+        if self.params.iter().any(|param| param.rest) {
+            return None;
+        }
+        Some(self.params.len())
+    }
+
+    /// Checks the keyword argument names of a call that also passes
+    /// `positional` arguments, returning the first bad one and what is wrong.
+    pub fn check_keywords<'a>(&self, positional: usize, keywords: &[&'a Token]) -> Result<(), (&'a Token, String)> {
+        for (i, name) in keywords.iter().copied().enumerate() {
+            if keywords[..i].iter().any(|seen| seen.lexeme == name.lexeme) {
+                return Err((name, format!("Duplicate keyword argument '{}'.", name.lexeme)));
+            }
+            match self.params.iter().position(|param| param.name.lexeme == name.lexeme) {
+                None => return Err((name, format!("'{}' has no parameter named '{}'.", self.name.lexeme, name.lexeme))),
+                Some(i) if self.params[i].rest => return Err((name, format!("Rest parameter '{}' can't be passed by keyword.", name.lexeme))),
+                Some(i) if i < positional => return Err((name, format!("Got multiple values for parameter '{}'.", name.lexeme))),
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
 }
 
-impl StmtAccept for Stmt  {
-    fn accept <'a> (&self, visitor: &'a mut dyn StmtVisitor) -> Result<(), RuntimeException> {
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: Token,
+    pub constant: bool,
+    pub default: Option<Box<Expr>>,
+    pub rest: bool,
+}
+
+impl Param {
+    pub fn new(name: Token, constant: bool, default: Option<Box<Expr>>, rest: bool) -> Self {
+        Param { name, constant, default, rest }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportStmt {
+    pub keyword: Token,
+    pub path: String,
+    pub alias: Option<Token>,
+    pub names: Vec<Token>,
+}
+
+impl ImportStmt {
+    pub fn new(keyword: Token, path: String, alias: Option<Token>, names: Vec<Token>) -> Self {
+        ImportStmt { keyword, path, alias, names }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchStmt {
+    pub keyword: Token,
+    pub subject: Expr,
+    pub arms: Vec<MatchArm>,
+}
+
+impl MatchStmt {
+    pub fn new(keyword: Token, subject: Expr, arms: Vec<MatchArm>) -> Self {
+        MatchStmt { keyword, subject, arms }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: MatchPattern,
+    pub guard: Option<Expr>,
+    pub body: Stmt,
+}
+
+impl MatchArm {
+    pub fn new(pattern: MatchPattern, guard: Option<Expr>, body: Stmt) -> Self {
+        MatchArm { pattern, guard, body }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchPattern {
+    Literal(Literal),
+    // Lower and upper bound, and whether the upper bound is included.
+    Range(f64, f64, bool),
+    Wildcard,
+    Binding(Token),
+    Alternatives(Vec<MatchPattern>),
+}
+
+impl MatchPattern {
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            MatchPattern::Wildcard | MatchPattern::Binding(_) => true,
+            MatchPattern::Alternatives(alternatives) => alternatives.iter().any(MatchPattern::is_irrefutable),
+            MatchPattern::Literal(_) | MatchPattern::Range(..) => false,
+        }
+    }
+
+    pub fn matches(&self, value: &Literal) -> bool {
+        match self {
+            MatchPattern::Literal(literal) => literal == value,
+            MatchPattern::Range(low, high, inclusive) => match value {
+                Literal::Number(n) => *n >= *low && (*n < *high || (*inclusive && *n == *high)),
+                _ => false,
+            },
+            MatchPattern::Wildcard | MatchPattern::Binding(_) => true,
+            MatchPattern::Alternatives(alternatives) => alternatives.iter().any(|alternative| alternative.matches(value)),
+        }
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            StmtKind::ExprStmt(expr) => write!(f, "(expr {})", expr),
+            StmtKind::PrintStmt(expr) => write!(f, "(print {})", expr),
+            StmtKind::Declaration { name, initializer, constant } => {
+                write!(f, "({} {}", if *constant { "const" } else { "var" }, name.lexeme)?;
+                if let Some(initializer) = initializer {
+                    write!(f, " {}", initializer)?;
+                }
+                write!(f, ")")
+            }
+            StmtKind::Destructure { pattern, initializer, constant } => {
+                write!(f, "({} {} {})", if *constant { "const" } else { "var" }, pattern, initializer)
+            }
+            StmtKind::Block(stmts) => {
+                write!(f, "(block")?;
+                for stmt in stmts.iter() {
+                    write!(f, " {}", stmt)?;
+                }
+                write!(f, ")")
+            }
+            StmtKind::Function(fun_stmt) => {
+                write!(f, "(fun {} (", fun_stmt.name.lexeme)?;
+                for (i, param) in fun_stmt.params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", param)?;
+                }
+                write!(f, ")")?;
+                for stmt in fun_stmt.body.iter() {
+                    write!(f, " {}", stmt)?;
+                }
+                write!(f, ")")
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                write!(f, "(if {} {}", condition, then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    write!(f, " {}", else_branch)?;
+                }
+                write!(f, ")")
+            }
+            StmtKind::While(condition, body) => write!(f, "(while {} {})", condition, body),
+            StmtKind::DoWhile(body, condition) => write!(f, "(do {} {})", body, condition),
+            StmtKind::ForIn(name, iterable, body) => write!(f, "(for-in {} {} {})", name.lexeme, iterable, body),
+            StmtKind::Return(_, value) => match value {
+                Some(value) => write!(f, "(return {})", value),
+                None => write!(f, "(return)"),
+            },
+            StmtKind::Import(import) => {
+                write!(f, "(import \"{}\"", import.path)?;
+                if let Some(alias) = &import.alias {
+                    write!(f, " as {}", alias.lexeme)?;
+                }
+                if !import.names.is_empty() {
+                    write!(f, " (")?;
+                    for (i, name) in import.names.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, " ")?;
+                        }
+                        write!(f, "{}", name.lexeme)?;
+                    }
+                    write!(f, ")")?;
+                }
+                write!(f, ")")
+            }
+            StmtKind::Match(match_stmt) => {
+                write!(f, "(match {}", match_stmt.subject)?;
+                for arm in match_stmt.arms.iter() {
+                    write!(f, " (=> {}", arm.pattern)?;
+                    if let Some(guard) = &arm.guard {
+                        write!(f, " (if {})", guard)?;
+                    }
+                    write!(f, " {})", arm.body)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = if self.rest { format!("...{}", self.name.lexeme) } else { self.name.lexeme.clone() };
+        let name = if self.constant { format!("(const {})", name) } else { name };
+        match &self.default {
+            Some(default) => write!(f, "(= {} {})", name, default),
+            None => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for MatchPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Stmt::ExprStmt(es) => visitor.visit_expression_stmt(es),
-            Stmt::PrintStmt(ps) => visitor.visit_print_stmt(ps),
-            Stmt::Declaration { id, initializer } => visitor.visit_declaration(id, initializer),
-            Stmt::Block(v) => visitor.visit_block(v),
-            Stmt::If(cond, fi, esl) => visitor.visit_if(cond, fi, esl),
-            Stmt::While(expr, st) => visitor.visit_while(expr, st),
-            Stmt::Function(fun_stmt) => visitor.visit_function(fun_stmt),
-            Stmt::Return(tok, exp) => visitor.visit_return(tok, exp),
+            MatchPattern::Literal(literal) => write!(f, "{}", ExprKind::Lit(literal.clone())),
+            MatchPattern::Range(low, high, inclusive) => {
+                write!(f, "{:?}{}{:?}", low, if *inclusive { "..=" } else { ".." }, high)
+            }
+            MatchPattern::Wildcard => write!(f, "_"),
+            MatchPattern::Binding(name) => write!(f, "{}", name.lexeme),
+            MatchPattern::Alternatives(alternatives) => {
+                write!(f, "(|")?;
+                for alternative in alternatives {
+                    write!(f, " {}", alternative)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl StmtAccept for Stmt  {
+    fn accept  (&self, visitor: &mut dyn StmtVisitor) -> Result<(), RuntimeException> {
+        match &self.kind {
+            StmtKind::ExprStmt(es) => visitor.visit_expression_stmt(es),
+            StmtKind::PrintStmt(ps) => visitor.visit_print_stmt(ps),
+            StmtKind::Declaration { name, initializer, constant } => visitor.visit_declaration(name, initializer, *constant),
+            StmtKind::Destructure { pattern, initializer, constant } => visitor.visit_destructure(pattern, initializer, *constant),
+            StmtKind::Block(v) => visitor.visit_block(v),
+            StmtKind::If(cond, fi, esl) => visitor.visit_if(cond, fi, esl),
+            StmtKind::While(expr, st) => visitor.visit_while(expr, st),
+            StmtKind::DoWhile(st, expr) => visitor.visit_do_while(st, expr),
+            StmtKind::ForIn(name, iterable, st) => visitor.visit_for_in(name, iterable, st),
+            StmtKind::Function(fun_stmt) => visitor.visit_function(fun_stmt),
+            StmtKind::Return(tok, exp) => visitor.visit_return(tok, exp),
+            StmtKind::Import(import) => visitor.visit_import(import),
+            StmtKind::Match(match_stmt) => visitor.visit_match(match_stmt),
         }
     }
 }
\ No newline at end of file
@@ -8,8 +8,14 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
     Dot,
+    DotDot,
+    DotDotEqual,
+    DotDotDot,
     Minus,
     Plus,
     SemiColon,
@@ -23,16 +29,22 @@ pub enum TokenType {
     Slash,
     Bang,
     Equal,
+    FatArrow,
+    Pipe,
     String(String),
     Identifier,
     Number(f64),
     And,
     Class,
+    Const,
+    Do,
     Else,
     False,
     For,
     Fun,
     If,
+    Import,
+    Match,
     Nil,
     Or,
     Print,
@@ -42,24 +54,32 @@ pub enum TokenType {
     True,
     Var,
     While,
-    EOF
+    Eof
 }
 
 impl TokenType {
-    fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             TokenType::LeftParen => "LEFT_PAREN",
             TokenType::RightParen => "RIGHT_PAREN",
             TokenType::LeftBrace => "LEFT_BRACE",
             TokenType::RightBrace => "RIGHT_BRACE",
+            TokenType::LeftBracket => "LEFT_BRACKET",
+            TokenType::RightBracket => "RIGHT_BRACKET",
             TokenType::Comma => "COMMA",
+            TokenType::Colon => "COLON",
             TokenType::Dot => "DOT",
+            TokenType::DotDot => "DOT_DOT",
+            TokenType::DotDotEqual => "DOT_DOT_EQUAL",
+            TokenType::DotDotDot => "DOT_DOT_DOT",
             TokenType::Minus => "MINUS",
             TokenType::Plus => "PLUS",
             TokenType::Star => "STAR",
             TokenType::SemiColon => "SEMICOLON",
             TokenType::Slash => "SLASH",
             TokenType::Equal => "EQUAL",
+            TokenType::FatArrow => "FAT_ARROW",
+            TokenType::Pipe => "PIPE",
             TokenType::EqualEqual => "EQUAL_EQUAL",
             TokenType::Bang => "BANG",
             TokenType::BangEqual => "BANG_EQUAL",
@@ -72,11 +92,15 @@ impl TokenType {
             TokenType::Identifier => "IDENTIFIER",
             TokenType::And => "AND",
             TokenType::Class => "CLASS",
+            TokenType::Const => "CONST",
+            TokenType::Do => "DO",
             TokenType::Else => "ELSE",
             TokenType::False => "FALSE",
             TokenType::Fun => "FUN",
             TokenType::For => "FOR",
             TokenType::If => "IF",
+            TokenType::Import => "IMPORT",
+            TokenType::Match => "MATCH",
             TokenType::Nil => "NIL",
             TokenType::Or => "OR",
             TokenType::Print => "PRINT",
@@ -86,7 +110,7 @@ impl TokenType {
             TokenType::True => "TRUE",
             TokenType::Var => "VAR",
             TokenType::While => "WHILE",
-            TokenType::EOF => "EOF",
+            TokenType::Eof => "EOF",
         }
     }
 }
@@ -97,12 +121,63 @@ impl fmt::Display for TokenType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<Box<Literal>>,
     pub line: usize,
+    pub column: usize,
+}
+
+// Like the ids and spans of nodes, where a token sits isn't part of what it is.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type && self.lexeme == other.lexeme && self.literal == other.literal
+    }
+}
+
+impl Token {
+    pub fn span(&self) -> Span {
+        // Only a string can hold a newline, and then it ends on a later line.
+        match self.lexeme.rsplit_once('\n') {
+            Some((before, last)) => Span {
+                line: self.line,
+                column: self.column,
+                end_line: self.line + before.matches('\n').count() + 1,
+                end_column: last.chars().count() + 1,
+            },
+            None => Span {
+                line: self.line,
+                column: self.column,
+                end_line: self.line,
+                end_column: self.column + self.lexeme.chars().count(),
+            },
+        }
+    }
+}
+
+/// A stretch of source from `line:column` up to, but not including,
+/// `end_line:end_column`. Lines and columns count from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    /// The span covering both `self` and everything up to the end of `end`.
+    pub fn to(self, end: Span) -> Span {
+        Span { end_line: end.end_line, end_column: end.end_column, ..self }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}-{}:{}", self.line, self.column, self.end_line, self.end_column)
+    }
 }
 
 impl fmt::Display for Token {
@@ -111,11 +186,13 @@ impl fmt::Display for Token {
             match &self.literal {
                 Some(boxed_value) => {
                     match &**boxed_value {
-                        Literal::Str(literal) => format!("{}", literal),
+                        Literal::Str(literal) => literal.to_string(),
                         Literal::Number(literal) => format!("{:?}", literal),
                         Literal::Boolean(literal) => format!("{}", literal),
                         Literal::LoxCallable(literal) => format!("{}", literal),
-                        Literal::Nil => format!("null")
+                        Literal::Namespace(literal) => format!("{}", literal),
+                        Literal::List(_) | Literal::Object(_) => format!("{}", boxed_value),
+                        Literal::Nil => "null".to_string()
                     }
                 }
                 None => "null".to_owned(),
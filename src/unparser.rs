@@ -0,0 +1,325 @@
+use crate::{expr::{Expr, ExprKind, Literal, Pattern}, parser::{infix_binding, Associativity, Precedence}, stmt::{MatchPattern, Param, Stmt, StmtKind}};
+
+/// Turns statements back into Lox source.
+///
+/// Parentheses are only written where the tree has a `Grouping` or where the
+/// parser's precedence table would otherwise read the expression differently,
+/// so anything `Parser::_parse` produced parses back to an equal tree. Trees
+/// built by hand may gain `Grouping` nodes or blocks on the way through.
+pub fn unparse(stmts: &[Stmt]) -> String {
+    let mut source = String::new();
+    for stmt in stmts {
+        source.push_str(&statement(stmt, 0));
+        source.push('\n');
+    }
+    source
+}
+
+fn pad(indent: usize) -> String {
+    "    ".repeat(indent)
+}
+
+fn statement(stmt: &Stmt, indent: usize) -> String {
+    match &stmt.kind {
+        StmtKind::ExprStmt(expr) => {
+            let source = expression(expr);
+            // At the start of a statement '{' opens a block, unless an object
+            // pattern is being assigned to.
+            if source.starts_with('{') && !matches!(expr.kind, ExprKind::Destructure(..)) {
+                return format!("({});", source);
+            }
+            format!("{};", source)
+        }
+        StmtKind::PrintStmt(expr) => format!("print {};", expression(expr)),
+        StmtKind::Declaration { name, initializer, constant } => {
+            let keyword = if *constant { "const" } else { "var" };
+            match initializer {
+                Some(initializer) => format!("{} {} = {};", keyword, name.lexeme, expression(initializer)),
+                None => format!("{} {};", keyword, name.lexeme),
+            }
+        }
+        StmtKind::Destructure { pattern: target, initializer, constant } => {
+            let keyword = if *constant { "const" } else { "var" };
+            format!("{} {} = {};", keyword, pattern(target), expression(initializer))
+        }
+        StmtKind::Block(stmts) => block(stmts, indent),
+        StmtKind::Function(fun_stmt) => {
+            let params: Vec<String> = fun_stmt.params.iter().map(param).collect();
+            format!("fun {}({}) {}", fun_stmt.name.lexeme, params.join(", "), block(&fun_stmt.body, indent))
+        }
+        StmtKind::If(condition, then_branch, else_branch) => {
+            let mut source = format!("if ({}) ", expression(condition));
+            match else_branch {
+                Some(else_branch) => {
+                    // An else-less `if` here would steal this `else` when read back.
+                    if dangles(then_branch) {
+                        source.push_str(&block(std::slice::from_ref(&**then_branch), indent));
+                    }
+                    else {
+                        source.push_str(&body(then_branch, indent));
+                    }
+                    source.push_str(" else ");
+                    source.push_str(&body(else_branch, indent));
+                }
+                None => source.push_str(&body(then_branch, indent)),
+            }
+            source
+        }
+        StmtKind::While(condition, st) => format!("while ({}) {}", expression(condition), body(st, indent)),
+        StmtKind::DoWhile(st, condition) => format!("do {} while ({});", body(st, indent), expression(condition)),
+        StmtKind::ForIn(name, iterable, st) => format!("for ({} in {}) {}", name.lexeme, expression(iterable), body(st, indent)),
+        StmtKind::Return(_, value) => match value {
+            Some(value) => format!("return {};", expression(value)),
+            None => "return;".to_string(),
+        },
+        StmtKind::Import(import) => match &import.alias {
+            Some(alias) => format!("import \"{}\" as {};", import.path, alias.lexeme),
+            None => {
+                let names: Vec<&str> = import.names.iter().map(|name| name.lexeme.as_str()).collect();
+                format!("import {{ {} }} from \"{}\";", names.join(", "), import.path)
+            }
+        },
+        StmtKind::Match(match_stmt) => {
+            let mut source = format!("match ({}) {{\n", expression(&match_stmt.subject));
+            for arm in match_stmt.arms.iter() {
+                source.push_str(&pad(indent + 1));
+                source.push_str(&match_pattern(&arm.pattern));
+                if let Some(guard) = &arm.guard {
+                    source.push_str(&format!(" if {}", expression(guard)));
+                }
+                source.push_str(&format!(" => {}\n", body(&arm.body, indent + 1)));
+            }
+            source.push_str(&pad(indent));
+            source.push('}');
+            source
+        }
+    }
+}
+
+fn block(stmts: &[Stmt], indent: usize) -> String {
+    if stmts.is_empty() {
+        return "{}".to_string();
+    }
+
+    let mut source = "{\n".to_string();
+    for stmt in stmts {
+        source.push_str(&pad(indent + 1));
+        source.push_str(&statement(stmt, indent + 1));
+        source.push('\n');
+    }
+    source.push_str(&pad(indent));
+    source.push('}');
+    source
+}
+
+// The statement under an `if`, loop or match arm, where declarations aren't
+// allowed on their own.
+fn body(stmt: &Stmt, indent: usize) -> String {
+    match &stmt.kind {
+        StmtKind::Declaration { .. } | StmtKind::Destructure { .. } | StmtKind::Function(_) | StmtKind::Import(_) => {
+            block(std::slice::from_ref(stmt), indent)
+        }
+        _ => statement(stmt, indent),
+    }
+}
+
+fn dangles(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::If(_, _, None) => true,
+        StmtKind::If(_, _, Some(else_branch)) => dangles(else_branch),
+        StmtKind::While(_, st) | StmtKind::ForIn(_, _, st) => dangles(st),
+        _ => false,
+    }
+}
+
+fn param(param: &Param) -> String {
+    let mut source = String::new();
+    if param.constant {
+        source.push_str("const ");
+    }
+    if param.rest {
+        source.push_str("...");
+    }
+    source.push_str(&param.name.lexeme);
+    if let Some(default) = &param.default {
+        source.push_str(&format!(" = {}", expression(default)));
+    }
+    source
+}
+
+fn pattern(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Name(name) => name.lexeme.clone(),
+        Pattern::List(_, elements, rest) => {
+            let mut parts: Vec<String> = elements.iter().map(self::pattern).collect();
+            if let Some(rest) = rest {
+                parts.push(format!("...{}", rest.lexeme));
+            }
+            format!("[{}]", parts.join(", "))
+        }
+        Pattern::Object(_, fields) => {
+            let parts: Vec<String> = fields.iter()
+                .map(|(key, field)| match field {
+                    Pattern::Name(name) if name.lexeme == key.lexeme => key.lexeme.clone(),
+                    _ => format!("{}: {}", key.lexeme, self::pattern(field)),
+                })
+                .collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+    }
+}
+
+fn match_pattern(pattern: &MatchPattern) -> String {
+    match pattern {
+        MatchPattern::Literal(literal) => self::literal(literal),
+        MatchPattern::Range(low, high, inclusive) => format!("{}{}{}", low, if *inclusive { "..=" } else { ".." }, high),
+        MatchPattern::Wildcard => "_".to_string(),
+        MatchPattern::Binding(name) => name.lexeme.clone(),
+        MatchPattern::Alternatives(alternatives) => {
+            let parts: Vec<String> = alternatives.iter().map(match_pattern).collect();
+            parts.join(" | ")
+        }
+    }
+}
+
+fn literal(literal: &Literal) -> String {
+    match literal {
+        Literal::Number(n) => format!("{}", n),
+        Literal::Str(s) => format!("\"{}\"", s),
+        Literal::Boolean(b) => format!("{}", b),
+        Literal::Nil => "nil".to_string(),
+        Literal::List(items) => {
+            let parts: Vec<String> = items.borrow().iter().map(self::literal).collect();
+            format!("[{}]", parts.join(", "))
+        }
+        Literal::Object(object) => {
+            let parts: Vec<String> = object.borrow().fields().iter()
+                .map(|(name, value)| format!("{}: {}", name, self::literal(value)))
+                .collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+        // Runtime values with no source form.
+        Literal::LoxCallable(_) | Literal::Namespace(_) => format!("{}", literal),
+    }
+}
+
+fn precedence(expr: &Expr) -> Precedence {
+    match &expr.kind {
+        ExprKind::Assign(..) | ExprKind::Destructure(..) => Precedence::Assignment,
+        ExprKind::Binary(_, operator, _) | ExprKind::Logical(_, operator, _) => infix_binding(&operator.token_type)
+            .map(|(precedence, _)| precedence)
+            .unwrap_or(Precedence::Assignment),
+        ExprKind::Unary(..) => Precedence::Unary,
+        ExprKind::Lit(Literal::Number(n)) if n.is_sign_negative() => Precedence::Unary,
+        _ => Precedence::Call,
+    }
+}
+
+// Source for an expression in a position the parser reads with
+// `parse_precedence(min)`.
+fn operand(expr: &Expr, min: Precedence) -> String {
+    // Destructuring is only recognised where a full expression starts.
+    if matches!(expr.kind, ExprKind::Destructure(..)) || precedence(expr) < min {
+        return format!("({})", expression(expr));
+    }
+    expression(expr)
+}
+
+fn infix(left: &Expr, operator: &str, right: &Expr, precedence: Precedence, associativity: Associativity) -> String {
+    let right_min = match associativity {
+        Associativity::Left => precedence.next(),
+        Associativity::Right => precedence,
+    };
+    format!("{} {} {}", operand(left, precedence), operator, operand(right, right_min))
+}
+
+/// Source for a single expression.
+pub fn expression(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Lit(lit) => literal(lit),
+        ExprKind::Grouping(inner) => format!("({})", expression(inner)),
+        ExprKind::Unary(operator, right) => format!("{}{}", operator.lexeme, operand(right, Precedence::Unary)),
+        ExprKind::Binary(left, operator, right) | ExprKind::Logical(left, operator, right) => {
+            let (precedence, associativity) = infix_binding(&operator.token_type)
+                .unwrap_or((Precedence::Assignment, Associativity::Left));
+            infix(left, &operator.lexeme, right, precedence, associativity)
+        }
+        ExprKind::Variable(name) => name.lexeme.clone(),
+        ExprKind::Assign(name, value) => format!("{} = {}", name.lexeme, operand(value, Precedence::Assignment)),
+        ExprKind::Destructure(target, value) => format!("{} = {}", pattern(target), expression(value)),
+        ExprKind::Call(callee, _, arguments, keywords) => {
+            let mut parts: Vec<String> = arguments.iter().map(expression).collect();
+            parts.extend(keywords.iter().map(|keyword| format!("{}: {}", keyword.name.lexeme, expression(&keyword.value))));
+            format!("{}({})", operand(callee, Precedence::Call), parts.join(", "))
+        }
+        ExprKind::Get(object, name) => format!("{}.{}", operand(object, Precedence::Call), name.lexeme),
+        ExprKind::Index(object, _, index) => format!("{}[{}]", operand(object, Precedence::Call), expression(index)),
+        ExprKind::List(elements) => {
+            let parts: Vec<String> = elements.iter().map(expression).collect();
+            format!("[{}]", parts.join(", "))
+        }
+        ExprKind::Object(properties) => {
+            let parts: Vec<String> = properties.iter()
+                .map(|property| match &property.value.kind {
+                    ExprKind::Variable(name) if name.lexeme == property.name.lexeme => name.lexeme.clone(),
+                    _ => format!("{}: {}", property.name.lexeme, expression(&property.value)),
+                })
+                .collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parser::Parser, scanner::Scanner, stmt::Stmt};
+
+    use super::unparse;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+        Parser::new(tokens)._parse().expect("the source should parse")
+    }
+
+    #[test]
+    fn unparsed_source_parses_back_to_the_same_tree() {
+        // Every kind of statement and expression at least once.
+        let source = r#"
+            import "shapes.lox" as shapes;
+            import { area, perimeter } from "shapes.lox";
+            var count;
+            const limit = 10;
+            var [first, [second], ...others] = [1, [2], 3, 4];
+            var {name, size: {width}} = {name: "box", size: {width: 2}};
+            fun scale(factor, offset = 0, ...rest) {
+                return factor * (width + offset) - -first;
+            }
+            {
+                var local = !true or false and nil == nil;
+                print local;
+            }
+            if (count == nil) count = 1; else count = count + 1;
+            while (count < limit) count = count + 1;
+            do { count = count - 1; } while (count > 0);
+            for (item in [1, 2, 3]) print item;
+            match (count) {
+                0 => print "none";
+                1 | 2 => print "few";
+                3..5 => print "some";
+                6..=9 => print "many";
+                n if n > limit => print n;
+                _ => print "lots";
+            }
+            var box = {name, size: scale(2, offset: 1)};
+            print box.name + others[0];
+            [first, second] = [second, first];
+            {name} = box;
+            shapes.area(box);
+        "#;
+
+        let stmts = parse(source);
+        let reparsed = parse(&unparse(&stmts));
+        assert_eq!(reparsed, stmts);
+    }
+}